@@ -0,0 +1,166 @@
+use std::{
+  env,
+  fs::{File, OpenOptions},
+  io::{self, IsTerminal, Write},
+  path::PathBuf,
+  process,
+  sync::atomic::{AtomicU64, Ordering},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How `FlexiLoggerView` copies the selected entries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMethod {
+  /// OSC 52 if stdout is a terminal which is likely to support it, a temp
+  /// file otherwise.
+  #[default]
+  Auto,
+  /// Send the text to the terminal's clipboard with an OSC 52 escape
+  /// sequence.
+  Osc52,
+  /// Write the text to a new file in the temp directory, e.g.
+  /// `flexi_logger_view_copy_1234_1700000000123456789_0.txt` (pid, time and
+  /// a counter), readable only by the current user.
+  TempFile,
+}
+
+/// Where the text was copied to.
+#[derive(Debug)]
+pub(crate) enum Copied {
+  Clipboard,
+  File(PathBuf),
+}
+
+pub(crate) fn copy(text: &str, method: CopyMethod) -> io::Result<Copied> {
+  match method {
+    CopyMethod::Osc52 => copy_osc52(text),
+    CopyMethod::TempFile => copy_to_file(text),
+    _ if supports_osc52() => copy_osc52(text),
+    _ => copy_to_file(text),
+  }
+}
+
+/// There is no way to query OSC 52 support, so only rule out the terminals
+/// known to lack it.
+fn supports_osc52() -> bool {
+  io::stdout().is_terminal()
+    && env::var("TERM").is_ok_and(|term| !matches!(term.as_str(), "dumb" | "linux"))
+}
+
+fn copy_osc52(text: &str) -> io::Result<Copied> {
+  let mut stdout = io::stdout().lock();
+  write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+  stdout.flush()?;
+  Ok(Copied::Clipboard)
+}
+
+fn copy_to_file(text: &str) -> io::Result<Copied> {
+  let (mut file, path) = create_temp_file()?;
+  file.write_all(text.as_bytes())?;
+  Ok(Copied::File(path))
+}
+
+/// Creates a new file with a unique name in the (possibly shared) temp
+/// directory.
+///
+/// `create_new` fails instead of following a symlink or truncating a file
+/// that someone else planted at the path.
+fn create_temp_file() -> io::Result<(File, PathBuf)> {
+  static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_nanos());
+  let mut options = OpenOptions::new();
+  options
+    .write(true)
+    .create_new(true);
+  #[cfg(unix)]
+  std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+  let mut attempts = 0;
+  loop {
+    let path = env::temp_dir().join(format!(
+      "flexi_logger_view_copy_{}_{nanos}_{}.txt",
+      process::id(),
+      COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    match options.open(&path) {
+      Ok(file) => return Ok((file, path)),
+      Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 16 => {
+        attempts += 1
+      }
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  bytes
+    .chunks(3)
+    .flat_map(|chunk| {
+      let n = chunk
+        .iter()
+        .enumerate()
+        .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+
+      // A chunk of `k` bytes is encoded as `k + 1` chars, padded with `=`
+      (0..4).map(move |i| match i <= chunk.len() {
+        true => ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char,
+        _ => '=',
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use super::*;
+
+  #[test]
+  fn base64_rfc4648_vectors() {
+    let vectors = [
+      ("", ""),
+      ("f", "Zg=="),
+      ("fo", "Zm8="),
+      ("foo", "Zm9v"),
+      ("foob", "Zm9vYg=="),
+      ("fooba", "Zm9vYmE="),
+      ("foobar", "Zm9vYmFy"),
+    ];
+
+    for (input, encoded) in vectors {
+      assert_eq!(base64(input.as_bytes()), encoded, "input `{input}`");
+    }
+    assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0xfc]), "//79/A==");
+  }
+
+  #[test]
+  fn temp_files_are_unique_and_private() {
+    let (Copied::File(a), Copied::File(b)) =
+      (copy_to_file("a").unwrap(), copy_to_file("b").unwrap())
+    else {
+      panic!("expected files");
+    };
+
+    assert_ne!(a, b);
+    assert_eq!(fs::read_to_string(&a).unwrap(), "a");
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      let mode = fs::metadata(&a)
+        .unwrap()
+        .permissions()
+        .mode();
+      assert_eq!(mode & 0o777, 0o600);
+    }
+
+    fs::remove_file(a).unwrap();
+    fs::remove_file(b).unwrap();
+  }
+}
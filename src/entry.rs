@@ -0,0 +1,141 @@
+use std::thread;
+
+use chrono::{DateTime, Local};
+use compact_str::{format_compact, CompactString};
+use flexi_logger::{DeferredNow, Record};
+use getset::Getters;
+use log::{
+  kv::{self, Key, Value, VisitSource},
+  Level,
+};
+use tap::Pipe;
+
+/// A structured log record, as stored in the log buffer.
+///
+/// Unlike a pre-rendered `StyledString`, every field of the original
+/// `log::Record` is kept, so the `FlexiLoggerView` can filter, re-format and
+/// search the history after the fact.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct LogEntry {
+  level: Level,
+  target: CompactString,
+  module_path: Option<CompactString>,
+  file: Option<CompactString>,
+  line: Option<u32>,
+  /// Name of the thread that emitted the record (if it has one).
+  thread: Option<CompactString>,
+  timestamp: DateTime<Local>,
+  message: CompactString,
+  /// Key-value pairs attached via the `log` crate's `kv` feature.
+  key_values: Vec<(CompactString, CompactString)>,
+  /// Number of identical consecutive records collapsed into this entry (1
+  /// unless the channel deduplicates, see `LogChannel::set_dedup`).
+  repeats: u64,
+  /// Timestamp of the last of the `repeats` records.
+  last_timestamp: DateTime<Local>,
+}
+
+impl LogEntry {
+  /// Captures everything we need from a `Record`.
+  ///
+  /// Note: This must be called on the logging thread, otherwise the thread
+  /// name would be wrong.
+  pub(crate) fn from_record(now: &mut DeferredNow, record: &Record) -> Self {
+    let mut key_values = KeyValueCollector::default();
+    // A visitor that only collects pairs can not fail.
+    let _ = record
+      .key_values()
+      .visit(&mut key_values);
+
+    let timestamp = *now.now();
+
+    Self {
+      level: record.level(),
+      target: record.target().into(),
+      module_path: record
+        .module_path()
+        .map(Into::into),
+      file: record.file().map(Into::into),
+      line: record.line(),
+      thread: thread::current()
+        .name()
+        .map(Into::into),
+      timestamp,
+      message: record
+        .args()
+        .pipe(|x| format_compact!("{x}")),
+      key_values: key_values.0,
+      repeats: 1,
+      last_timestamp: timestamp,
+    }
+  }
+
+  /// An `Info` entry logged now, for unit tests.
+  #[cfg(test)]
+  pub(crate) fn test(target: &str, message: &str) -> Self {
+    Self::from_record(
+      &mut DeferredNow::new(),
+      &Record::builder()
+        .args(format_args!("{message}"))
+        .level(Level::Info)
+        .target(target)
+        .build(),
+    )
+  }
+
+  /// Whether `other` repeats this entry: same level, target and message.
+  pub(crate) fn is_repeated_by(&self, other: &Self) -> bool {
+    self.level == other.level
+      && self.target == other.target
+      && self.message == other.message
+  }
+
+  /// Counts `other` as another repeat of this entry.
+  pub(crate) fn repeat(&mut self, other: &Self) {
+    self.repeats += 1;
+    self.last_timestamp = other.timestamp;
+  }
+
+  /// Approximate memory used by this entry (including heap allocations).
+  pub(crate) fn size_bytes(&self) -> usize {
+    let strings = [
+      Some(&self.target),
+      self.module_path.as_ref(),
+      self.file.as_ref(),
+      self.thread.as_ref(),
+      Some(&self.message),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(
+      self
+        .key_values
+        .iter()
+        .flat_map(|(k, v)| [k, v]),
+    )
+    .map(CompactString::len)
+    .sum::<usize>();
+
+    core::mem::size_of::<Self>()
+      + strings
+      + self.key_values.capacity()
+        * core::mem::size_of::<(CompactString, CompactString)>()
+  }
+}
+
+#[derive(Default)]
+struct KeyValueCollector(Vec<(CompactString, CompactString)>);
+
+impl<'kvs> VisitSource<'kvs> for KeyValueCollector {
+  fn visit_pair(
+    &mut self,
+    key: Key<'kvs>,
+    value: Value<'kvs>,
+  ) -> Result<(), kv::Error> {
+    self
+      .0
+      .push((key.as_str().into(), format_compact!("{value}")));
+    Ok(())
+  }
+}
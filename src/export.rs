@@ -0,0 +1,225 @@
+use core::fmt::Write as _;
+use std::{
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::Path,
+};
+
+use cursive_core::{
+  theme::{BaseColor, Color, ColorType, ConcreteEffects, Effect, Style},
+  utils::markup::StyledString,
+};
+use getset::{CopyGetters, WithSetters};
+use tap::Pipe;
+
+use crate::{FlexiLoggerView, LogEntry};
+
+/// Output format of `FlexiLoggerView::export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+  /// The rendered lines, without colors.
+  #[default]
+  PlainText,
+  /// The rendered lines, colored with ANSI escape sequences.
+  Ansi,
+  /// One JSON object per entry, with all of its fields.
+  JsonLines,
+}
+
+/// Options of `FlexiLoggerView::export`.
+///
+/// ```rust
+/// use cursive_logger_view::{ExportFormat, ExportOptions};
+///
+/// ExportOptions::default()
+///     .with_format(ExportFormat::JsonLines)
+///     .with_filtered(false);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters, WithSetters)]
+#[getset(get_copy = "pub", set_with = "pub")]
+pub struct ExportOptions {
+  format: ExportFormat,
+  /// Only export the entries passing the filters of the view (default:
+  /// `true`).
+  filtered: bool,
+}
+
+impl Default for ExportOptions {
+  fn default() -> Self {
+    Self {
+      format: ExportFormat::default(),
+      filtered: true,
+    }
+  }
+}
+
+impl FlexiLoggerView {
+  /// Saves the log history of this view to a file.
+  ///
+  /// If the view is paused, the paused snapshot is exported.
+  pub fn export(
+    &self,
+    path: impl AsRef<Path>,
+    options: ExportOptions,
+  ) -> io::Result<()> {
+    let mut file = File::create(path)?.pipe(BufWriter::new);
+    self.export_to(&mut file, options)?;
+    file.flush()
+  }
+
+  /// Writes the log history of this view to `writer`.
+  pub fn export_to(
+    &self,
+    writer: &mut impl Write,
+    options: ExportOptions,
+  ) -> io::Result<()> {
+    self.with_logs(|logs| {
+      logs
+        .iter_from(0)
+        .map(|(entry, _)| entry)
+        .filter(|entry| !options.filtered || self.is_visible(entry))
+        .try_for_each(|entry| {
+          let line = match options.format {
+            ExportFormat::PlainText => plain_line(&self.render_entry(entry).0),
+            ExportFormat::Ansi => ansi_line(&self.render_entry(entry).0),
+            ExportFormat::JsonLines => json_line(entry),
+          };
+          writeln!(writer, "{line}")
+        })
+    })
+  }
+}
+
+pub(crate) fn plain_line(line: &StyledString) -> String {
+  line
+    .spans()
+    .map(|span| span.content)
+    .collect()
+}
+
+/// Renders a `StyledString` with ANSI escape sequences.
+///
+/// Palette colors depend on the cursive theme, so they are left to the
+/// terminal default.
+pub(crate) fn ansi_line(line: &StyledString) -> String {
+  line
+    .spans()
+    .collect::<Vec<_>>()
+    // Adjacent spans often share a style (e.g. a level and its brackets)
+    .chunk_by(|a, b| a.attr == b.attr)
+    .fold(String::new(), |mut out, spans| {
+      let content = spans
+        .iter()
+        .map(|span| span.content)
+        .collect::<String>();
+
+      match sgr_codes(spans[0].attr) {
+        codes if codes.is_empty() => out.push_str(&content),
+        codes => {
+          let _ = write!(out, "\x1b[{codes}m{content}\x1b[0m");
+        }
+      }
+      out
+    })
+}
+
+/// Select Graphic Rendition parameters of a style, e.g. `1;32`.
+fn sgr_codes(style: &Style) -> String {
+  let mut codes = style
+    .effects
+    .resolve(ConcreteEffects::empty())
+    .iter()
+    .filter_map(|effect| match effect {
+      Effect::Bold => Some("1"),
+      Effect::Dim => Some("2"),
+      Effect::Italic => Some("3"),
+      Effect::Underline => Some("4"),
+      Effect::Blink => Some("5"),
+      Effect::Reverse => Some("7"),
+      Effect::Strikethrough => Some("9"),
+      Effect::Simple => None,
+    })
+    .map(String::from)
+    .collect::<Vec<_>>();
+
+  [(style.color.front, 30), (style.color.back, 40)]
+    .into_iter()
+    .filter_map(|(color, base)| match color {
+      ColorType::Color(color) => color_code(color, base),
+      _ => None,
+    })
+    .for_each(|code| codes.push(code));
+
+  codes.join(";")
+}
+
+/// `base` is `30` for foreground and `40` for background colors.
+fn color_code(color: Color, base: u8) -> Option<String> {
+  let ansi = |c: BaseColor| c as u8;
+
+  match color {
+    Color::TerminalDefault => None,
+    Color::Dark(c) => (base + ansi(c))
+      .to_string()
+      .into(),
+    Color::Light(c) => (base + 60 + ansi(c))
+      .to_string()
+      .into(),
+    Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8).into(),
+    Color::RgbLowRes(r, g, b) => {
+      format!("{};5;{}", base + 8, 16 + 36 * r + 6 * g + b).into()
+    }
+  }
+}
+
+/// Serializes all fields of an entry as a single JSON object.
+fn json_line(entry: &LogEntry) -> String {
+  let optional = |s: Option<&str>| s.map_or_else(|| "null".into(), json_string);
+
+  let key_values = entry
+    .key_values()
+    .iter()
+    .map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)))
+    .collect::<Vec<_>>()
+    .join(",");
+
+  format!(
+    r#"{{"timestamp":{},"level":{},"target":{},"module_path":{},"file":{},"line":{},"thread":{},"message":{},"key_values":{{{key_values}}},"repeats":{},"last_timestamp":{}}}"#,
+    json_string(&entry.timestamp().to_rfc3339()),
+    json_string(entry.level().as_str()),
+    json_string(entry.target()),
+    optional(entry.module_path().as_deref()),
+    optional(entry.file().as_deref()),
+    entry
+      .line()
+      .map_or_else(|| "null".into(), |l| l.to_string()),
+    optional(entry.thread().as_deref()),
+    json_string(entry.message()),
+    entry.repeats(),
+    json_string(
+      &entry
+        .last_timestamp()
+        .to_rfc3339()
+    ),
+  )
+}
+
+fn json_string(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str(r#"\""#),
+      '\\' => out.push_str(r"\\"),
+      '\n' => out.push_str(r"\n"),
+      '\r' => out.push_str(r"\r"),
+      '\t' => out.push_str(r"\t"),
+      c if c.is_control() => {
+        let _ = write!(out, "\\u{:04x}", c as u32);
+      }
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
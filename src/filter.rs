@@ -0,0 +1,148 @@
+use core::{fmt, str::FromStr};
+use std::collections::BTreeMap;
+
+use compact_str::CompactString;
+use getset::{Getters, WithSetters};
+use log::{LevelFilter, ParseLevelError};
+
+use crate::LogEntry;
+
+/// Matches entries carrying a key-value pair with the given key (and
+/// optionally the given value).
+///
+/// ```rust
+/// use cursive_logger_view::{FlexiLoggerView, KeyFilter};
+///
+/// // log::info!(user = "bob"; "logged in");
+/// FlexiLoggerView::new()
+///     .with_key_filter(Some(KeyFilter::new("user").with_value(Some("bob".into()))));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Getters, WithSetters)]
+#[getset(get = "pub")]
+pub struct KeyFilter {
+  key: CompactString,
+  #[getset(set_with = "pub")]
+  value: Option<CompactString>,
+}
+
+impl KeyFilter {
+  pub fn new(key: impl Into<CompactString>) -> Self {
+    Self {
+      key: key.into(),
+      value: None,
+    }
+  }
+
+  pub fn matches(&self, entry: &LogEntry) -> bool {
+    entry
+      .key_values()
+      .iter()
+      .any(|(k, v)| {
+        *k == self.key
+          && self
+            .value
+            .as_ref()
+            .is_none_or(|value| v == value)
+      })
+  }
+}
+
+/// Minimum levels by target, like the directives of `RUST_LOG`.
+///
+/// The directive of the longest matching target prefix (on `::` boundaries)
+/// decides, `LevelFilter::Off` hides a target. The empty target is the
+/// default for all targets, and entries without any matching directive are
+/// shown.
+///
+/// ```rust
+/// use cursive_logger_view::{FlexiLoggerView, ModuleFilter};
+/// use log::LevelFilter;
+///
+/// let filter: ModuleFilter = "info,my_app::net=trace,hyper=off".parse().unwrap();
+/// assert_eq!(filter.level_for("my_app::net::tcp"), Some(LevelFilter::Trace));
+/// assert_eq!(filter.level_for("my_app::ui"), Some(LevelFilter::Info));
+/// assert_eq!(filter.to_string(), "info,hyper=off,my_app::net=trace");
+///
+/// FlexiLoggerView::new().with_module_filter(filter);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModuleFilter {
+  directives: BTreeMap<CompactString, LevelFilter>,
+}
+
+impl ModuleFilter {
+  pub fn directives(&self) -> &BTreeMap<CompactString, LevelFilter> {
+    &self.directives
+  }
+
+  pub fn set(&mut self, target: impl Into<CompactString>, level: LevelFilter) {
+    self
+      .directives
+      .insert(target.into(), level);
+  }
+
+  /// Removes the directive of `target`, so it inherits the level of its
+  /// parent.
+  pub fn remove(&mut self, target: &str) {
+    self.directives.remove(target);
+  }
+
+  /// The minimum level of `target`, `None` if no directive matches.
+  pub fn level_for(&self, target: &str) -> Option<LevelFilter> {
+    self
+      .directives
+      .iter()
+      .filter(|(prefix, _)| {
+        prefix.is_empty()
+          || target
+            .strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+      })
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, &level)| level)
+  }
+
+  pub fn matches(&self, entry: &LogEntry) -> bool {
+    self
+      .level_for(entry.target())
+      .is_none_or(|level| *entry.level() <= level)
+  }
+}
+
+impl fmt::Display for ModuleFilter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let directives = self
+      .directives
+      .iter()
+      .map(|(target, level)| {
+        let level = level
+          .as_str()
+          .to_ascii_lowercase();
+        match target.is_empty() {
+          true => level,
+          _ => format!("{target}={level}"),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    write!(f, "{}", directives.join(","))
+  }
+}
+
+impl FromStr for ModuleFilter {
+  type Err = ParseLevelError;
+
+  /// Parses comma-separated `target=level` or `level` (the default)
+  /// directives.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.split(',')
+      .map(str::trim)
+      .filter(|directive| !directive.is_empty())
+      .map(|directive| match directive.split_once('=') {
+        Some((target, level)) => Ok((target.trim().into(), level.trim().parse()?)),
+        _ => Ok((CompactString::default(), directive.parse()?)),
+      })
+      .collect::<Result<_, _>>()
+      .map(|directives| Self { directives })
+  }
+}
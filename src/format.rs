@@ -0,0 +1,355 @@
+use cursive_core::{align::HAlign, theme::Style, utils::markup::StyledString};
+use getset::{CopyGetters, Getters, WithSetters};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{wrap, LogItems};
+
+/// How a `FormatItem` is styled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ItemStyle {
+  /// The style the `LogTheme` of the view gives to this kind of item.
+  #[default]
+  Theme,
+  /// The style of the entry's level.
+  Level,
+  Fixed(Style),
+  /// No style at all.
+  Plain,
+}
+
+impl ItemStyle {
+  /// `theme` is the style of the `LogTheme` for the item (`None` if it has
+  /// none).
+  pub(crate) const fn resolve(
+    self,
+    theme: Option<Style>,
+    level: Style,
+  ) -> Option<Style> {
+    match self {
+      Self::Theme => theme,
+      Self::Level => Some(level),
+      Self::Fixed(style) => Some(style),
+      Self::Plain => None,
+    }
+  }
+}
+
+/// The side of a value that `FormatItem::max_width` cuts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Truncate {
+  /// Keep the start of the value.
+  #[default]
+  End,
+  /// Keep the end of the value, e.g. the innermost module of a path.
+  Start,
+}
+
+/// A `LogItems` field of the format of the `FlexiLoggerView`, with its
+/// options.
+///
+/// ```rust
+/// use cursive_logger_view::{FlexiLoggerView, FormatItem, ItemStyle, LogItems::*};
+///
+/// // Only color the level, keep the messages plain
+/// FlexiLoggerView::new().with_format(
+///     [
+///         DateTime.into(),
+///         FormatItem::from(Level).with_style(ItemStyle::Level),
+///         ModLine.into(),
+///         FormatItem::from(Message).with_style(ItemStyle::Plain),
+///     ]
+///     .into_iter()
+///     .collect(),
+/// );
+/// ```
+///
+/// Pad and cut values, so the items line up as columns:
+///
+/// ```rust
+/// use cursive::align::HAlign;
+/// use cursive_logger_view::{FlexiLoggerView, FormatItem, LogItems::*, Truncate};
+///
+/// FlexiLoggerView::new().with_format(
+///     [
+///         DateTime.into(),
+///         FormatItem::from(Level).with_width(Some(5)).with_align(HAlign::Right),
+///         // `…ger_view::view:42`
+///         FormatItem::from(ModLine)
+///             .with_width(Some(30))
+///             .with_max_width(Some(30))
+///             .with_truncate(Truncate::Start)
+///             .with_ellipsis(true),
+///         Message.into(),
+///     ]
+///     .into_iter()
+///     .collect(),
+/// );
+/// ```
+///
+/// See `parse_format` for building the items from a template string.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters, WithSetters)]
+pub struct FormatItem {
+  #[getset(get = "pub")]
+  item: LogItems<'static>,
+  #[getset(get_copy = "pub", set_with = "pub")]
+  style: ItemStyle,
+  /// Pads the value with spaces to this width.
+  #[getset(get_copy = "pub", set_with = "pub")]
+  width: Option<usize>,
+  /// Alignment of the value in its `width` (default: `HAlign::Left`).
+  #[getset(get_copy = "pub", set_with = "pub")]
+  align: HAlign,
+  /// Cuts the value to this width.
+  #[getset(get_copy = "pub", set_with = "pub")]
+  max_width: Option<usize>,
+  /// The side cut from values longer than `max_width`.
+  #[getset(get_copy = "pub", set_with = "pub")]
+  truncate: Truncate,
+  /// Marks cut values with a `…` (which counts towards `max_width`).
+  #[getset(get_copy = "pub", set_with = "pub")]
+  ellipsis: bool,
+  /// Wraps the value in the usual brackets of the item, followed by a space
+  /// (e.g. `[INFO] `). `true` by default, `false` for template items.
+  #[getset(get_copy = "pub", set_with = "pub")]
+  decorated: bool,
+}
+
+impl Default for FormatItem {
+  fn default() -> Self {
+    LogItems::default().into()
+  }
+}
+
+impl From<LogItems<'static>> for FormatItem {
+  fn from(item: LogItems<'static>) -> Self {
+    Self {
+      item,
+      style: ItemStyle::default(),
+      width: None,
+      align: HAlign::Left,
+      max_width: None,
+      truncate: Truncate::default(),
+      ellipsis: false,
+      decorated: true,
+    }
+  }
+}
+
+impl FormatItem {
+  /// Cuts and pads a rendered value according to `max_width`, `truncate`,
+  /// `ellipsis`, `width` and `align`.
+  pub(crate) fn fit(&self, value: StyledString) -> StyledString {
+    self.fit_padded(value).0
+  }
+
+  /// `fit`, also returning the width of the padding left of the value.
+  ///
+  /// Multi-line values are padded by their first line, which the padding is
+  /// next to.
+  pub(crate) fn fit_padded(&self, value: StyledString) -> (StyledString, usize) {
+    let value = match self.max_width {
+      Some(max) => self.cut(value, max),
+      _ => value,
+    };
+
+    let first_line = value
+      .source()
+      .split('\n')
+      .next()
+      .unwrap_or_default()
+      .chars()
+      .map(|c| c.width().unwrap_or(0))
+      .sum::<usize>();
+    let padding = self
+      .width
+      .unwrap_or(0)
+      .saturating_sub(first_line);
+    let (left, right) = match self.align {
+      HAlign::Left => (0, padding),
+      HAlign::Center => (padding / 2, padding - padding / 2),
+      HAlign::Right => (padding, 0),
+    };
+
+    let pad = |n: usize| match n {
+      0 => StyledString::new(),
+      n => StyledString::plain(" ".repeat(n)),
+    };
+
+    let mut fitted = pad(left);
+    fitted.append(value);
+    fitted.append(pad(right));
+    (fitted, left)
+  }
+
+  /// Cuts `value` to `max` columns, if it is wider.
+  ///
+  /// Widths are summed per char (as `fitting` does), which can be less than
+  /// the width of the whole string, e.g. for "❤️" (U+2764 U+FE0F).
+  fn cut(&self, value: StyledString, max: usize) -> StyledString {
+    let mut chars = value
+      .spans()
+      .flat_map(|span| {
+        span
+          .content
+          .chars()
+          .map(move |c| (c, *span.attr, c.width().unwrap_or(0)))
+      })
+      .collect::<Vec<_>>();
+
+    if chars
+      .iter()
+      .map(|&(.., w)| w)
+      .sum::<usize>()
+      <= max
+    {
+      return value;
+    }
+
+    let ellipsis = self.ellipsis && max > 0;
+    let keep = max - usize::from(ellipsis);
+
+    // Number of chars that fit in `keep` columns, from the kept side
+    let fitting = |chars: &mut dyn Iterator<Item = &(char, Style, usize)>| {
+      let mut width = 0;
+      chars
+        .take_while(|&&(.., w)| {
+          width += w;
+          width <= keep
+        })
+        .count()
+    };
+
+    match self.truncate {
+      Truncate::End => {
+        let n = fitting(&mut chars.iter());
+        // Styled like the first char that is cut off
+        let style = chars
+          .get(n)
+          .or(chars.last())
+          .map_or_else(Style::none, |c| c.1);
+        chars.truncate(n);
+        if ellipsis {
+          chars.push(('…', style, 1));
+        }
+      }
+      Truncate::Start => {
+        let n = fitting(&mut chars.iter().rev());
+        let style = chars
+          .iter()
+          .rev()
+          .nth(n)
+          .or(chars.first())
+          .map_or_else(Style::none, |c| c.1);
+        chars.drain(..chars.len() - n);
+        if ellipsis {
+          chars.insert(0, ('…', style, 1));
+        }
+      }
+    }
+
+    wrap::styled(&chars)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use cursive_core::theme::Effect;
+
+  use super::*;
+
+  fn fit(item: FormatItem, value: &str) -> String {
+    item
+      .fit(StyledString::plain(value))
+      .source()
+      .to_owned()
+  }
+
+  fn cut(max: usize, truncate: Truncate, ellipsis: bool) -> FormatItem {
+    FormatItem::from(LogItems::Message)
+      .with_max_width(Some(max))
+      .with_truncate(truncate)
+      .with_ellipsis(ellipsis)
+  }
+
+  #[test]
+  fn cut_end_and_start() {
+    use Truncate::{End, Start};
+
+    assert_eq!(fit(cut(4, End, false), "abcdef"), "abcd");
+    assert_eq!(fit(cut(4, End, true), "abcdef"), "abc…");
+    assert_eq!(fit(cut(4, Start, false), "abcdef"), "cdef");
+    assert_eq!(fit(cut(4, Start, true), "abcdef"), "…def");
+    // Values that fit are left alone
+    assert_eq!(fit(cut(6, End, true), "abcdef"), "abcdef");
+    assert_eq!(fit(cut(0, End, true), "abcdef"), "");
+  }
+
+  #[test]
+  fn cut_wide_chars() {
+    use Truncate::{End, Start};
+
+    // Each char is 2 columns wide, so a cut may leave a column unused
+    assert_eq!(fit(cut(4, End, false), "日本語"), "日本");
+    assert_eq!(fit(cut(3, End, false), "日本語"), "日");
+    assert_eq!(fit(cut(4, End, true), "日本語"), "日…");
+    assert_eq!(fit(cut(4, Start, false), "日本語"), "本語");
+    assert_eq!(fit(cut(4, Start, true), "日本語"), "…語");
+    assert_eq!(fit(cut(1, Start, true), "日本語"), "…");
+  }
+
+  #[test]
+  fn cut_zero_width_chars() {
+    use Truncate::{End, Start};
+
+    // U+2764 U+FE0F: 2 columns as a string, but 1 + 0 per char
+    for truncate in [End, Start] {
+      assert_eq!(fit(cut(1, truncate, false), "❤\u{fe0f}"), "❤\u{fe0f}");
+      assert_eq!(fit(cut(1, truncate, true), "❤\u{fe0f}"), "❤\u{fe0f}");
+    }
+    assert_eq!(fit(cut(2, End, true), "❤\u{fe0f}ab"), "❤\u{fe0f}…");
+    assert_eq!(fit(cut(2, Start, true), "ab❤\u{fe0f}"), "…❤\u{fe0f}");
+  }
+
+  #[test]
+  fn cut_keeps_styles() {
+    let mut value = StyledString::styled("ab", Effect::Bold);
+    value.append_plain("cd");
+
+    let cut = cut(3, Truncate::End, true).fit(value);
+    let spans = cut
+      .spans()
+      .map(|span| (span.content, *span.attr))
+      .collect::<Vec<_>>();
+
+    // The ellipsis takes the style of the first char it replaces
+    assert_eq!(
+      spans,
+      [("ab", Style::from(Effect::Bold)), ("…", Style::none())]
+    );
+  }
+
+  #[test]
+  fn pad_and_align() {
+    let item = |align| {
+      FormatItem::from(LogItems::Level)
+        .with_width(Some(5))
+        .with_align(align)
+    };
+
+    assert_eq!(fit(item(HAlign::Left), "ab"), "ab   ");
+    assert_eq!(fit(item(HAlign::Center), "ab"), " ab  ");
+    assert_eq!(fit(item(HAlign::Right), "ab"), "   ab");
+    assert_eq!(fit(item(HAlign::Right), "abcdefg"), "abcdefg");
+    // Multi-line values are padded by their first line
+    assert_eq!(fit(item(HAlign::Right), "a\nbcdefg"), "    a\nbcdefg");
+    assert_eq!(
+      fit(
+        item(HAlign::Right)
+          .with_max_width(Some(5))
+          .with_ellipsis(true),
+        "abcdefg"
+      ),
+      "abcd…"
+    );
+  }
+}
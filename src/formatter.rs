@@ -1,169 +1,235 @@
-use std::{io, thread};
-
-use compact_str::{format_compact, CompactString, ToCompactString};
-use cursive_core::{
-  theme::{BaseColor, Color},
-  utils::markup::StyledString,
+use std::{
+  io,
+  sync::{atomic::Ordering, Arc},
 };
+
+use compact_str::{format_compact, ToCompactString};
+use cursive_core::{theme::Style, utils::markup::StyledString};
 use flexi_logger::{writers::LogWriter, DeferredNow, Record};
-use getset::WithSetters;
-use log::Level;
 use tap::Pipe;
 
 use crate::{
-  log_buffer::{self, GET_LOCK_ERR_MSG},
-  CursiveLogWriter, LogItems,
+  panic, shutdown::PendingRefresh, CursiveLogWriter, FlexiLoggerView, LogEntry,
+  LogItems,
 };
 
-const fn log_level_as_dark_color(level: &Level) -> Color {
-  use BaseColor::{Cyan, Green, Magenta, Red, Yellow};
-  use Level::*;
-  let base_color = match level {
-    Trace => Magenta,
-    Debug => Cyan,
-    Info => Green,
-    Warn => Yellow,
-    Error => Red,
-  };
-  Color::Dark(base_color)
-}
+/// Brackets of a decorated item, see `FormatItem::decorated`.
+const fn decorations(item: &LogItems) -> (&'static str, &'static str) {
+  use crate::LogItems::*;
 
-#[derive(Debug, WithSetters)]
-struct StyledTextConfig<'a> {
-  line: &'a mut StyledString,
-  #[getset(set_with)]
-  content: CompactString,
-  #[getset(set_with)]
-  color_enabled: bool,
-  color: Color,
+  match item {
+    Thread | Level => ("[", "] "),
+    ModLine | File | FileLine => ("<", "> "),
+    DateTime | Target | KeyValues => ("", " "),
+    Message | Custom(_) | Text(_) => ("", ""),
+  }
 }
 
-impl StyledTextConfig<'_> {
-  fn append_line(mut self) -> Self {
-    let content = (&mut self.content) //
-      .pipe(core::mem::take);
-
-    match self.color_enabled {
-      true => self
-        .line
-        .append_styled(content, self.color),
-      _ => self.line.append_plain(content),
-    }
-    self
+fn styled_text(text: &str, style: Option<Style>) -> StyledString {
+  match style {
+    _ if text.is_empty() => StyledString::new(),
+    Some(style) => StyledString::styled(text, style),
+    _ => StyledString::plain(text),
   }
+}
 
-  fn append_mod_line(self, rec: &Record) -> Self {
-    let (path, line_num) = (
-      rec.module_path().unwrap_or(""), //
-      rec.line().unwrap_or(0),
-    );
-
-    format_compact!("<{path}:") //
-      .pipe(|s| self.line.append_plain(s));
+impl FlexiLoggerView {
+  /// Renders a structured `LogEntry` into a `StyledString`, according to the
+  /// `format`, `time_format` and `theme` of this view.
+  ///
+  /// Also returns the column where the message starts (if the format has a
+  /// `Message`), which continuation lines of the message are indented to.
+  pub(crate) fn render_entry(
+    &self,
+    entry: &LogEntry,
+  ) -> (StyledString, Option<usize>) {
+    let level_style = self.theme.level(*entry.level());
+
+    self.format.iter().fold(
+      (StyledString::new(), None),
+      |(mut line, mut message_x), item| {
+        // Style of the parts of the item that the theme styles as `theme`
+        let style = |theme: Option<Style>| {
+          item
+            .style()
+            .resolve(theme, level_style)
+        };
+        let (value, theme) = self.render_item(item.item(), entry, style);
+
+        let (open, close) = match item.decorated() {
+          // No key-value pairs, no trailing space
+          true if value.is_empty() && *item.item() == LogItems::KeyValues => {
+            return (line, message_x);
+          }
+          true => decorations(item.item()),
+          _ => ("", ""),
+        };
 
-    format_compact!("{line_num}") //
-      .pipe(|s| {
-        self
-          .line
-          .append_styled(s, Color::Dark(BaseColor::Blue))
-      });
+        line.append(styled_text(open, style(theme)));
+        let (value, padding) = item.fit_padded(value);
+        if *item.item() == LogItems::Message {
+          message_x.get_or_insert(line.width() + padding);
+        }
+        line.append(value);
+        line.append(styled_text(close, style(theme)));
+        (line, message_x)
+      },
+    )
+  }
 
-    self.line.append_plain("> ");
+  /// Renders the value of an item, and returns it with the theme style of its
+  /// brackets.
+  pub(crate) fn render_item(
+    &self,
+    item: &LogItems,
+    entry: &LogEntry,
+    style: impl Fn(Option<Style>) -> Option<Style>,
+  ) -> (StyledString, Option<Style>) {
+    use crate::LogItems::*;
+
+    let level_style = Some(self.theme.level(*entry.level()));
+    let text = |text: &str, theme| (styled_text(text, style(theme)), theme);
+
+    match item {
+      DateTime => entry
+        .timestamp()
+        .format(&self.time_format)
+        .to_compact_string()
+        .pipe(|s| text(&s, Some(self.theme.date_time()))),
+      //
+      Thread => entry
+        .thread()
+        .as_deref()
+        .unwrap_or(" ")
+        .pipe(|name| text(name, Some(self.theme.thread()))),
+      //
+      Level => entry
+        .level()
+        .as_str()
+        .pipe(|lv| text(lv, level_style)),
+      //
+      Target => text(entry.target(), Some(self.theme.module())),
+      //
+      File => entry
+        .file()
+        .as_deref()
+        .unwrap_or("")
+        .pipe(|file| text(file, Some(self.theme.module()))),
+      //
+      FileLine => format_compact!(
+        "{}:{}",
+        entry
+          .file()
+          .as_deref()
+          .unwrap_or(""),
+        entry.line().unwrap_or(0),
+      )
+      .pipe(|s| text(&s, Some(self.theme.module()))),
+      //
+      ModLine => {
+        let mut value = entry
+          .module_path()
+          .as_deref()
+          .unwrap_or("")
+          .pipe(|path| styled_text(path, style(Some(self.theme.module()))));
+        value.append(styled_text(":", style(None)));
+        format_compact!("{}", entry.line().unwrap_or(0)) //
+          .pipe(|s| {
+            value.append(styled_text(&s, style(Some(self.theme.line_number()))))
+          });
+
+        (value, None)
+      }
+      //
+      KeyValues => {
+        let value = entry
+          .key_values()
+          .iter()
+          .enumerate()
+          .fold(StyledString::new(), |mut value, (i, (k, v))| {
+            if i > 0 {
+              value.append(styled_text(" ", style(None)));
+            }
+            value.append(styled_text(k, style(Some(self.theme.key()))));
+            value.append(styled_text("=", style(None)));
+            value.append(styled_text(v, style(Some(self.theme.value()))));
+            value
+          });
+
+        (value, None)
+      }
+      //
+      Message => {
+        let (mut value, theme) = text(entry.message(), level_style);
+        if *entry.repeats() > 1 {
+          format_compact!(
+            " (×{}, last {})",
+            entry.repeats(),
+            entry
+              .last_timestamp()
+              .format(&self.time_format)
+          )
+          .pipe(|s| {
+            value.append(styled_text(&s, style(Some(self.theme.date_time()))))
+          });
+        }
+        (value, theme)
+      }
+      //
+      Custom(txt) => text(txt, level_style),
+      //
+      Text(txt) => text(txt, None),
+    }
+  }
+}
 
-    self
+impl CursiveLogWriter {
+  /// The entry of a record.
+  pub(crate) fn entry(
+    now: &mut DeferredNow,
+    record: &Record,
+  ) -> io::Result<LogEntry> {
+    // e.g. a panicking `Display` impl of a logged value must not unwind into
+    // the logging thread, which may be the one running cursive
+    panic::catch_unwind_silently(|| LogEntry::from_record(now, record))
+      .map_err(|_| io::Error::other("panicked while storing the log record"))
   }
 }
 
-impl LogWriter for CursiveLogWriter<'_> {
+impl LogWriter for CursiveLogWriter {
   fn write(&self, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
-    let styled_config = StyledTextConfig {
-      line: &mut StyledString::new(),
-      content: "".into(),
-      color_enabled: false,
-      color: record
-        .level()
-        .pipe_ref(log_level_as_dark_color),
-    };
-
-    let line = self
-      .format
-      .iter()
-      .fold(styled_config, |cfg, item| {
-        use crate::LogItems::{
-          DateTime, File, FileLine, Level, Message, ModLine, Thread,
-        };
-
-        match item {
-          DateTime => now
-            .format(&self.time_format)
-            .pipe(|fmt| format_compact!("{fmt} "))
-            .pipe(|s| cfg.with_content(s))
-            .with_color_enabled(false)
-            .append_line(),
-          //
-          Thread => thread::current()
-            .name()
-            .unwrap_or(" ")
-            .pipe(|name| format_compact!("[{name}] "))
-            .pipe(|s| cfg.with_content(s))
-            .with_color_enabled(false)
-            .append_line(),
-          //
-          Level => record
-            .level()
-            .pipe(|lv| format_compact!("[{lv}] "))
-            .pipe(|s| cfg.with_content(s))
-            .with_color_enabled(true)
-            .append_line(),
-          //
-          File => record
-            .file()
-            .unwrap_or("")
-            .pipe(|file| format_compact!("<{file}> "))
-            .pipe(|s| cfg.with_content(s))
-            .with_color_enabled(false)
-            .append_line(),
-          //
-          FileLine => format_compact!(
-            "<{}:{}> ",
-            record.file().unwrap_or(""),
-            record.line().unwrap_or(0),
-          )
-          .pipe(|s| cfg.with_content(s))
-          .with_color_enabled(false)
-          .append_line(),
-          //
-          ModLine => cfg.append_mod_line(record),
-          //
-          Message => record
-            .args()
-            .pipe(|x| format_compact!("{x}"))
-            .pipe(|s| cfg.with_content(s))
-            .with_color_enabled(true)
-            .append_line(),
-          //
-          LogItems::Custom(txt) => cfg
-            .with_content(txt.to_compact_string())
-            .with_color_enabled(true)
-            .append_line(), // line.append_plain(txt.as_str()),
-        }
-      })
-      .line
-      .pipe(core::mem::take);
+    if self.is_shut_down() {
+      return self.write_after_shutdown(now, record, false);
+    }
 
+    self
+      .channel
+      .push(Self::entry(now, record)?);
+
+    // The entry is pushed *before* checking the flag, so the pending callback
+    // (which clears the flag before cursive redraws) covers it.
+    if self
+      .refresh_pending
+      .swap(true, Ordering::AcqRel)
     {
-      log_buffer::static_logs()
-        .lock()
-        .expect(GET_LOCK_ERR_MSG)
-        .push_back(line);
+      return Ok(());
     }
 
-    let io_broken_pipe = |msg| io::Error::new(io::ErrorKind::BrokenPipe, msg);
+    let pending = PendingRefresh(Arc::clone(&self.refresh_pending));
+    let channel = self.channel.clone();
 
     self
       .sink
-      .send(Box::new(|_| {}))
-      .map_err(|_| io_broken_pipe("cursive callback sink is closed!"))
+      .send(Box::new(move |_| {
+        drop(pending);
+        channel.drain_queue();
+      }))
+      .or_else(|_| {
+        self
+          .shut_down
+          .store(true, Ordering::Release);
+        self.write_after_shutdown(now, record, true)
+      })
   }
 
   fn flush(&self) -> io::Result<()> {
@@ -172,6 +238,10 @@ impl LogWriter for CursiveLogWriter<'_> {
   }
 
   fn max_log_level(&self) -> log::LevelFilter {
-    log::LevelFilter::max()
+    self.max_level
+  }
+
+  fn shutdown(&self) {
+    self.dump_on_shutdown()
   }
 }
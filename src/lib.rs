@@ -34,16 +34,7 @@
 //!                 .directory("logs")
 //!                 .suppress_timestamp(),
 //!             // Create Cursive log writer and box it for dynamic dispatch
-//!             CursiveLogWriter::new(&siv)
-//!                 //// Optional format configuration (commented out example)
-//!                 // .with_format({
-//!                 //     use cursive_logger_view::LogItems::*;
-//!                 //     [Level, DateTime, ModLine, Message]
-//!                 //         .into_iter()
-//!                 //         .collect()
-//!                 // })
-//!                 // .with_time_format("%T%.6f".into())
-//!                 .into_boxed(),
+//!             CursiveLogWriter::new(&siv).into_boxed(),
 //!         )
 //!         .start()
 //!         .expect("failed to initialize logger!");
@@ -53,6 +44,16 @@
 //!         FlexiLoggerView::new()
 //!             // .with_indent(true)  // Optional indentation configuration
 //!             //
+//!             //// Optional format configuration (commented out example)
+//!             // .with_format({
+//!             //     use cursive_logger_view::{FormatItem, LogItems::*};
+//!             //     [Level, DateTime, ModLine, Message]
+//!             //         .into_iter()
+//!             //         .map(FormatItem::from)
+//!             //         .collect()
+//!             // })
+//!             // .with_time_format("%T%.6f".into())
+//!             // .with_theme(cursive_logger_view::LogTheme::bright())
 //!             .wrap_scroll_view(),
 //!     );
 //!
@@ -63,7 +64,7 @@
 //!
 //! ```rust
 //! use cursive::{Cursive, CursiveExt};
-//! use cursive_logger_view::{show_flexi_logger_debug_console, hide_flexi_logger_debug_console, toggle_flexi_logger_debug_console};
+//! use cursive_logger_view::toggle::{show_flexi_logger_debug_console, hide_flexi_logger_debug_console, toggle_flexi_logger_debug_console};
 //! use flexi_logger::Logger;
 //!     // we need to initialize cursive first, as the cursive-logger-view
 //!     // needs a cursive callback sink to notify cursive about screen refreshs
@@ -88,19 +89,52 @@
 //!     log::info!("test log message");
 //!     // siv.run();
 //! ```
+mod clipboard;
+mod entry;
+mod export;
+mod filter;
+mod format;
 mod formatter;
+mod line_index;
 mod log_buffer;
+mod module_tree;
+mod panic;
+mod queue;
+mod search;
+mod shutdown;
+mod table;
+mod template;
+mod theme;
 pub mod toggle;
 mod view;
+mod wrap;
+
+use std::sync::{atomic::AtomicBool, Arc};
 
 use compact_str::CompactString;
-use cursive_core::{CbSink, Cursive};
+use cursive_core::{view::ScrollStrategy, CbSink, Cursive};
 pub use flexi_logger;
 use flexi_logger::writers::LogWriter;
-use getset::WithSetters;
+use getset::{Getters, Setters, WithSetters};
+use log::LevelFilter;
 use tap::Pipe;
 use tinyvec::TinyVec;
 
+pub use crate::{
+  clipboard::CopyMethod,
+  entry::LogEntry,
+  export::{ExportFormat, ExportOptions},
+  filter::{KeyFilter, ModuleFilter},
+  format::{FormatItem, ItemStyle, Truncate},
+  log_buffer::{set_buffer_limits, BufferLimits, LogChannel},
+  module_tree::ModuleFilterView,
+  panic::PanicHook,
+  queue::{AsyncQueue, OverflowPolicy},
+  shutdown::ShutdownPolicy,
+  template::{parse_format, FormatParseError, FormatParseErrorKind},
+  theme::LogTheme,
+};
+
 const FLEXI_LOGGER_DEBUG_VIEW_NAME: &str = "_flexi_debug_view";
 
 /// The `FlexiLoggerView` displays log messages from the `cursive_flexi_logger`
@@ -111,54 +145,170 @@ const FLEXI_LOGGER_DEBUG_VIEW_NAME: &str = "_flexi_debug_view";
 ///
 /// FlexiLoggerView::new().with_indent(true);
 /// ```
-#[derive(Default, Debug, WithSetters)]
+///
+/// Hide trace and debug messages (can be changed at runtime with the keys
+/// `1`-`5`):
+///
+/// ```rust
+/// use cursive_logger_view::FlexiLoggerView;
+/// use log::LevelFilter;
+///
+/// let mut view = FlexiLoggerView::new().with_level_filter(LevelFilter::Info);
+/// view.set_level_filter(LevelFilter::Warn);
+/// ```
+///
+/// # Key bindings
+///
+/// - `1`-`5`: only show `Error`, `Warn`, `Info`, `Debug` or `Trace` (and more
+///   severe) messages
+/// - `/`: search (`Ctrl+R` toggles regex mode, `Enter` confirms, `Esc`
+///   clears), `n` / `N`: jump to the next / previous hit
+/// - `Up` / `Down`: select an entry, `Enter` shows all of its fields, `Esc`
+///   clears the selection
+/// - `v`: start (or drop) a range selection at the selected entry, `y`: copy
+///   the selected entries as plain text (see `CopyMethod`)
+/// - `p`: pause the live tail, or resume following it
+/// - `t`: toggle the table mode, in which `<` / `>` select a column, `+` / `-`
+///   resize it (`=` resets all widths), `h` hides it and `H` shows all columns
+///
+/// The log buffer stores structured `LogEntry` records, which are rendered
+/// lazily according to `format`, `time_format` and `theme`, so changing them
+/// also re-formats the existing history.
+#[derive(Debug, Getters, Setters, WithSetters)]
+#[getset(set_with = "pub")]
 pub struct FlexiLoggerView {
-  #[getset(set_with = "pub")]
   pub indent: bool,
+  /// Soft-wrap long lines at the view width, instead of scrolling
+  /// horizontally.
+  wrap: bool,
+  /// Set on layout, while `wrap` is enabled.
+  #[getset(skip)]
+  wrap_width: Option<usize>,
+  /// Render the fields of the `format` as columns, below a header row (`wrap`
+  /// is ignored in this mode).
+  ///
+  /// At runtime, the key `t` toggles it.
+  #[getset(set = "pub", set_with = "pub")]
+  table: bool,
+  #[getset(skip)]
+  columns: table::Columns,
+  format: TinyVec<[FormatItem; 8]>,
+  time_format: CompactString,
+  /// Colors and effects of the rendered entries.
+  #[getset(set = "pub", set_with = "pub")]
+  theme: LogTheme,
+  /// Entries above this level are hidden (but stay in the buffer).
+  ///
+  /// At runtime, the keys `1`-`5` set it to `Error`, `Warn`, `Info`, `Debug`
+  /// and `Trace`.
+  #[getset(set = "pub", set_with = "pub")]
+  level_filter: LevelFilter,
+  /// Only show entries with a matching key-value pair.
+  #[getset(set = "pub", set_with = "pub")]
+  key_filter: Option<KeyFilter>,
+  /// Minimum levels by target (see `ModuleFilter`), on top of
+  /// `level_filter`.
+  ///
+  /// The debug console edits it in the dialog of
+  /// `toggle::show_module_filter_dialog` (`Ctrl+F`).
+  #[getset(get = "pub", set = "pub", set_with = "pub")]
+  module_filter: ModuleFilter,
+  /// The channel to display (default: `LogChannel::global()`).
+  channel: LogChannel,
+  #[getset(skip)]
+  search: search::Search,
+  #[getset(skip)]
+  index: line_index::LineIndex,
+  /// Applied to the wrapping `ScrollView` after an event was handled.
+  #[getset(skip)]
+  scroll_request: Option<ScrollStrategy>,
+  /// Copy of the channel's buffer, taken when the view was paused.
+  #[getset(skip)]
+  snapshot: Option<log_buffer::LogBuffer>,
+  /// Visible records received by the channel since the view was paused
+  /// (including the repeats merged into entries, see
+  /// `LogChannel::set_dedup`).
+  #[getset(skip)]
+  new_records: u64,
+  /// Sequence number of the selected entry.
+  #[getset(skip)]
+  selected: Option<u64>,
+  /// Other end of the selected range of entries (`selected` is the cursor).
+  #[getset(skip)]
+  anchor: Option<u64>,
+  /// How `y` copies the selected entries.
+  #[getset(set = "pub", set_with = "pub")]
+  copy_method: CopyMethod,
+  /// Result of the last action, shown in the status bar until the next key.
+  #[getset(skip)]
+  notice: Option<CompactString>,
+}
+
+impl Default for FlexiLoggerView {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 ///Possible log items
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub enum LogItems<'c> {
   DateTime,
   Thread,
   ModLine,
   File,
   FileLine,
+  #[default]
   Level,
   Message,
+  /// Key-value pairs attached via the `log` crate's `kv` feature, rendered as
+  /// `key=value` (nothing if there are none).
+  KeyValues,
+  /// The target of the record (the module path, unless set explicitly).
+  Target,
   // ThreadLine,
   Custom(&'c str),
-}
-
-impl Default for LogItems<'_> {
-  fn default() -> Self {
-    Self::Level
-  }
+  /// Literal text, e.g. from a template (see `parse_format`).
+  Text(CompactString),
 }
 
 /// The `flexi_logger` `LogWriter` implementation for the `FlexiLoggerView`.
 ///
 /// Use the `boxed_flexi_log_writer` or `CursiveLogWriter::new` function to
 /// create an instance of this struct.
+///
+/// The writer does not format anything, it only stores a structured
+/// `LogEntry` per record. See `FlexiLoggerView::with_format` for the layout,
+/// and `LogChannel::set_async_queue` for not blocking the logging threads.
 #[derive(Debug, WithSetters)]
-#[getset(set_with = "pub")]
-pub struct CursiveLogWriter<'fmt> {
+pub struct CursiveLogWriter {
   sink: CbSink,
-  format: TinyVec<[LogItems<'fmt>; 8]>,
-  time_format: CompactString,
+  /// Reported to `flexi_logger` via `LogWriter::max_log_level`.
+  #[getset(set_with = "pub")]
+  max_level: LevelFilter,
+  /// The channel to write into (default: `LogChannel::global()`).
+  #[getset(set_with = "pub")]
+  channel: LogChannel,
+  /// Set while a refresh callback is queued in the `sink`, so heavy logging
+  /// sends at most one callback per frame instead of one per record.
+  refresh_pending: Arc<AtomicBool>,
+  /// What to do with records once cursive has exited (default:
+  /// `ShutdownPolicy::Buffer`).
+  #[getset(set_with = "pub")]
+  shutdown_policy: ShutdownPolicy,
+  /// Set once the `sink` was found closed.
+  shut_down: AtomicBool,
 }
 
-impl CursiveLogWriter<'_> {
+impl CursiveLogWriter {
   pub fn new(siv: &Cursive) -> Self {
-    use crate::LogItems::{DateTime, Level, Message, ModLine};
-
     Self {
       sink: siv.cb_sink().clone(),
-      format: [DateTime, Level, ModLine, Message]
-        .into_iter()
-        .collect(),
-      time_format: "%T%.3f".pipe(CompactString::const_new),
+      max_level: LevelFilter::max(),
+      channel: LogChannel::global(),
+      refresh_pending: Default::default(),
+      shutdown_policy: ShutdownPolicy::default(),
+      shut_down: AtomicBool::new(false),
     }
   }
 
@@ -173,9 +323,10 @@ impl CursiveLogWriter<'_> {
 ///
 /// Although, it is safe to create multiple cursive log writers, it may not be
 /// what you want. Each instance of a cursive log writer replicates the log
-/// messages in to `FlexiLoggerView`. When registering multiple cursive log
-/// writer instances, a single log messages will be duplicated by each log
-/// writer.
+/// messages in to its `LogChannel`. When registering multiple cursive log
+/// writer instances for the same channel, a single log messages will be
+/// duplicated by each log writer. Use `CursiveLogWriter::with_channel` to give
+/// each writer its own channel instead.
 ///
 /// # Registering the cursive log writer in `flexi_logger`
 ///
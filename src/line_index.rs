@@ -0,0 +1,391 @@
+use std::collections::VecDeque;
+
+use crate::{
+  log_buffer::LogBuffer,
+  search::Search,
+  table,
+  view::{Line, RenderKey},
+  FlexiLoggerView,
+};
+
+/// Layout of a single (visible) entry.
+#[derive(Debug)]
+struct IndexedEntry {
+  /// Sequence number of the entry in its `LogBuffer`.
+  seq: u64,
+  /// Absolute number of the first line, see `LineIndex::base_line`.
+  first_line: usize,
+  lines: usize,
+  width: usize,
+  /// Lines (relative to `first_line`) containing a search match.
+  hits: Vec<usize>,
+  /// `FlexiLoggerView::cell_widths` (in table mode only).
+  cells: Vec<usize>,
+  /// `LogEntry::repeats` when the entry was rendered.
+  repeats: u64,
+}
+
+/// Cached line index of the `FlexiLoggerView`.
+///
+/// Every visible entry is rendered once, when it is indexed. Afterwards only
+/// its line count and width are kept, so `draw` can find the entries of the
+/// visible line window without rendering the whole buffer.
+///
+/// The index is rebuilt from scratch whenever the rendering settings of the
+/// view (`RenderKey`) change.
+#[derive(Debug, Default)]
+pub(crate) struct LineIndex {
+  key: Option<RenderKey>,
+  entries: VecDeque<IndexedEntry>,
+  /// Sequence number of the next entry of the buffer to index.
+  next_seq: u64,
+  /// Absolute line number of the first indexed entry.
+  ///
+  /// Line numbers are absolute (i.e. they keep growing), so dropping old
+  /// entries does not require renumbering the remaining ones.
+  base_line: usize,
+  /// Absolute line number after the last indexed entry.
+  end_line: usize,
+}
+
+impl LineIndex {
+  /// Indexes new entries of the view's channel and forgets the dropped ones.
+  pub(crate) fn update(&mut self, key: RenderKey, view: &FlexiLoggerView) {
+    if self.key.as_ref() != Some(&key) {
+      *self = Self {
+        key: Some(key),
+        ..Default::default()
+      };
+    }
+
+    view.with_logs(|logs| self.index_logs(logs, view));
+  }
+
+  fn index_logs(&mut self, logs: &LogBuffer, view: &FlexiLoggerView) {
+    while self
+      .entries
+      .front()
+      .is_some_and(|e| e.seq < logs.front_seq())
+    {
+      self.entries.pop_front();
+      self.base_line = self
+        .entries
+        .front()
+        .map_or(self.end_line, |e| e.first_line);
+    }
+
+    // Repeats are only merged into the last entry of the buffer, so only the
+    // last indexed entry can have changed since it was rendered
+    if let Some(last) = self
+      .entries
+      .back()
+      .filter(|last| {
+        logs
+          .get(last.seq)
+          .is_some_and(|entry| *entry.repeats() != last.repeats)
+      })
+    {
+      self.next_seq = last.seq;
+      self.end_line -= last.lines;
+      self.entries.pop_back();
+    }
+
+    for (entry, seq) in logs
+      .iter_from(self.next_seq)
+      .filter(|(entry, _)| view.is_visible(entry))
+    {
+      let lines = view.entry_lines(entry, None);
+      let cells = match view.table {
+        true => view.cell_widths(entry),
+        _ => Vec::new(),
+      };
+      self.push(seq, *entry.repeats(), &lines, cells, &view.search);
+    }
+
+    self.next_seq = logs.next_seq();
+  }
+
+  fn push(
+    &mut self,
+    seq: u64,
+    repeats: u64,
+    lines: &[Line],
+    cells: Vec<usize>,
+    search: &Search,
+  ) {
+    let hits = match search.is_active() {
+      true => lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| search.is_match(&line.text))
+        .map(|(i, _)| i)
+        .collect(),
+      _ => Vec::new(),
+    };
+
+    self
+      .entries
+      .push_back(IndexedEntry {
+        seq,
+        first_line: self.end_line,
+        lines: lines.len(),
+        width: lines
+          .iter()
+          .map(Line::width)
+          .max()
+          .unwrap_or(0),
+        hits,
+        cells,
+        repeats,
+      });
+    self.end_line += lines.len();
+  }
+
+  pub(crate) fn total_lines(&self) -> usize {
+    self.end_line - self.base_line
+  }
+
+  /// Width of the longest line.
+  pub(crate) fn width(&self) -> usize {
+    self
+      .entries
+      .iter()
+      .map(|e| e.width)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Width of the table (in table mode), with the columns sized to fit all
+  /// indexed rows, like `draw` sizes them to fit the visible ones.
+  ///
+  /// Rows are indexed at their natural width, so the difference of the
+  /// padded cells is added to each.
+  pub(crate) fn table_width(&self, view: &FlexiLoggerView) -> usize {
+    let widths = view.fit_columns(
+      self
+        .entries
+        .iter()
+        .map(|e| e.cells.as_slice()),
+    );
+    let prefix = table::prefix_width(&widths);
+    let header = prefix
+      + widths
+        .last()
+        .copied()
+        .unwrap_or(0);
+
+    self
+      .entries
+      .iter()
+      .map(|e| {
+        e.width
+          .saturating_sub(table::prefix_width(&e.cells))
+          + prefix
+      })
+      .fold(header, core::cmp::max)
+  }
+
+  /// Line numbers (relative to the first line) containing a search match.
+  pub(crate) fn hits(&self) -> Vec<usize> {
+    self
+      .entries
+      .iter()
+      .flat_map(|e| {
+        e.hits
+          .iter()
+          .map(move |i| e.first_line - self.base_line + i)
+      })
+      .collect()
+  }
+
+  /// Lines (relative to the first line) of the entry `seq`, if indexed.
+  pub(crate) fn lines_of(&self, seq: u64) -> Option<core::ops::Range<usize>> {
+    let entry = self
+      .entries
+      .binary_search_by_key(&seq, |e| e.seq)
+      .ok()
+      .and_then(|i| self.entries.get(i))?;
+    let first_line = entry.first_line - self.base_line;
+
+    Some(first_line..first_line + entry.lines)
+  }
+
+  /// The indexed entry `offset` entries away from `seq` (clamped to the
+  /// first and last entries), or the last entry if there is no `seq`.
+  pub(crate) fn step(&self, seq: Option<u64>, offset: isize) -> Option<u64> {
+    let last = self
+      .entries
+      .len()
+      .checked_sub(1)?;
+    let position = match seq {
+      // An entry that is gone is replaced by its successor
+      Some(seq) => self
+        .entries
+        .partition_point(|e| e.seq < seq)
+        .saturating_add_signed(offset),
+      _ => last,
+    };
+
+    self
+      .entries
+      .get(position.min(last))
+      .map(|e| e.seq)
+  }
+
+  /// Entries overlapping the lines `top..bottom`, as `(seq, first_line)`.
+  pub(crate) fn window(
+    &self,
+    top: usize,
+    bottom: usize,
+  ) -> impl Iterator<Item = (u64, usize)> + '_ {
+    let (top, bottom) = (top + self.base_line, bottom + self.base_line);
+    let start = self
+      .entries
+      .partition_point(|e| e.first_line + e.lines <= top);
+
+    self
+      .entries
+      .range(start..)
+      .take_while(move |e| e.first_line < bottom)
+      .map(|e| (e.seq, e.first_line - self.base_line))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{BufferLimits, LogEntry};
+
+  fn push(logs: &mut LogBuffer, messages: &[&str]) {
+    messages
+      .iter()
+      .for_each(|m| logs.push_back(LogEntry::test("t", m)));
+  }
+
+  fn indexed(logs: &LogBuffer) -> LineIndex {
+    let mut index = LineIndex::default();
+    index.index_logs(logs, &FlexiLoggerView::new());
+    index
+  }
+
+  fn window(index: &LineIndex, top: usize, bottom: usize) -> Vec<(u64, usize)> {
+    index
+      .window(top, bottom)
+      .collect()
+  }
+
+  #[test]
+  fn multi_line_entries() {
+    let mut logs = LogBuffer::default();
+    push(&mut logs, &["a", "b\nc\nd", "e\nf"]);
+    let index = indexed(&logs);
+
+    assert_eq!(index.total_lines(), 6);
+    assert_eq!(window(&index, 0, 1), [(0, 0)]);
+    assert_eq!(window(&index, 1, 3), [(1, 1)]);
+    assert_eq!(window(&index, 3, 5), [(1, 1), (2, 4)]);
+    assert_eq!(window(&index, 6, 9), []);
+    assert_eq!(index.lines_of(1), Some(1..4));
+    assert_eq!(index.lines_of(3), None);
+  }
+
+  #[test]
+  fn evicted_entries_are_dropped() {
+    let mut logs = LogBuffer::default();
+    logs.set_limits(BufferLimits::default().with_max_entries(2));
+    push(&mut logs, &["a\nb", "c\nd\ne"]);
+    let view = FlexiLoggerView::new();
+    let mut index = indexed(&logs);
+
+    push(&mut logs, &["f"]);
+    index.index_logs(&logs, &view);
+
+    // Line numbers are relative to the first retained entry again
+    assert_eq!(index.base_line, 2);
+    assert_eq!(index.total_lines(), 4);
+    assert_eq!(window(&index, 0, 4), [(1, 0), (2, 3)]);
+    assert_eq!(index.lines_of(0), None);
+    assert_eq!(index.lines_of(2), Some(3..4));
+    assert_eq!(index.step(Some(0), 0), Some(1));
+
+    // Evicting more entries than were pushed since the last update
+    push(&mut logs, &["g", "h\ni", "j"]);
+    index.index_logs(&logs, &view);
+
+    assert_eq!(index.total_lines(), 3);
+    assert_eq!(window(&index, 0, 3), [(4, 0), (5, 2)]);
+  }
+
+  #[test]
+  fn all_entries_evicted() {
+    let mut logs = LogBuffer::default();
+    push(&mut logs, &["a\nb", "c"]);
+    let mut index = indexed(&logs);
+
+    logs.set_limits(BufferLimits::default().with_max_entries(0));
+    index.index_logs(&logs, &FlexiLoggerView::new());
+
+    assert_eq!(index.total_lines(), 0);
+    assert_eq!(index.base_line, 3);
+    assert_eq!(window(&index, 0, 10), []);
+    assert_eq!(index.step(None, 0), None);
+  }
+
+  #[test]
+  fn last_entry_is_reindexed_after_a_dedup_merge() {
+    let mut logs = LogBuffer::default();
+    logs.set_dedup(true);
+    push(&mut logs, &["a", "b\nc"]);
+    let view = FlexiLoggerView::new();
+    let mut index = indexed(&logs);
+    let width = index.width();
+
+    push(&mut logs, &["b\nc"]);
+    index.index_logs(&logs, &view);
+
+    assert_eq!(index.entries.len(), 2);
+    assert_eq!(index.entries[1].repeats, 2);
+    assert_eq!(index.total_lines(), 3);
+    // The `(×2, last …)` suffix
+    assert!(index.width() > width);
+
+    push(&mut logs, &["d"]);
+    index.index_logs(&logs, &view);
+
+    assert_eq!(window(&index, 0, 4), [(0, 0), (1, 1), (2, 3)]);
+  }
+
+  #[test]
+  fn table_width_includes_padded_columns() {
+    let mut logs = LogBuffer::default();
+    logs.push_back(LogEntry::test("t", "a long message"));
+    logs.push_back(LogEntry::test("long_target", "b\nc"));
+    let mut view = FlexiLoggerView::new()
+      .with_format_str("{target} {message}")
+      .unwrap()
+      .with_table(true);
+    let mut index = LineIndex::default();
+    index.index_logs(&logs, &view);
+
+    // "t" is padded to "long_target": 11 + " │ " + 14, and so is the header
+    assert_eq!(index.width(), 18);
+    assert_eq!(index.table_width(&view), 28);
+    let widths = view.column_widths(
+      logs
+        .iter_from(0)
+        .map(|(e, _)| e),
+    );
+    let drawn = logs
+      .iter_from(0)
+      .flat_map(|(e, _)| view.entry_lines(e, Some(&widths)))
+      .map(|line| line.width())
+      .max();
+    assert_eq!(drawn, Some(28));
+
+    // `+` widens the (selected) first column
+    view
+      .columns
+      .on_event(&cursive_core::event::Event::Char('+'), 2);
+    assert_eq!(index.table_width(&view), 29);
+  }
+}
@@ -1,42 +1,532 @@
-use std::sync::{Mutex, OnceLock};
+use core::fmt;
+use std::{
+  collections::{BTreeSet, VecDeque},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard, OnceLock, PoisonError, TryLockError,
+  },
+};
 
-use circular_buffer::CircularBuffer;
-use cursive_core::utils::markup::StyledString;
+use compact_str::CompactString;
+use getset::{CopyGetters, WithSetters};
 use tap::Pipe;
 
-/// Pre-defined error message for lock acquisition failures
-pub(crate) const GET_LOCK_ERR_MSG: &str = "Failed to get static_logs Mutex Lock";
-
-/// Type alias for a ring buffer with fixed capacity storing styled strings.
-///
-/// Why CircularBuffer?
-/// 1. Provides efficient FIFO (a.k.a. First In First Out) behavior with fixed
-///    capacity.
-/// 2. Automatically overwriting oldest entries when full.
-///
-/// ⚠️ Important Note
-/// - Ensure you call `LogBuffer::boxed()` instead of `LogBuffer::new()` for
-///   initialization.
-///   - Reason: This data-structure is very large. If allocated on the stack via
-///     `new()`, it could cause a stack overflow.
-type LogBuffer = CircularBuffer<3072, StyledString>;
-
-/// Thread-safe buffer type breakdown:
-/// - Box: Ensures buffer allocation stays on the heap
-/// - Mutex: Provides exclusive access synchronization
-type SyncBuffer = Mutex<Box<LogBuffer>>;
-
-/// Initializes and provides global access to the thread-safe log buffer
-///
-/// Why OnceLock? Ensures:
-/// 1. Thread-safe lazy initialization
-/// 2. Avoids "double initialization" race conditions
-/// 3. No unnecessary allocation before first use
-pub(crate) fn static_logs() -> &'static SyncBuffer {
-  static LOGS: OnceLock<SyncBuffer> = OnceLock::new();
-
-  LOGS.get_or_init(|| {
-    LogBuffer::boxed() // Create heap-allocated buffer (Box prevents stack overflow)
-      .pipe(Mutex::new)
-  })
+use crate::{queue::Queue, AsyncQueue, LogEntry};
+
+/// Limits of the log buffer, chosen at runtime.
+///
+/// When a limit is exceeded, the oldest entries are dropped.
+///
+/// ```rust
+/// use cursive_logger_view::{set_buffer_limits, BufferLimits};
+///
+/// // Keep up to 50k entries, but never more than 16 MiB.
+/// set_buffer_limits(
+///     BufferLimits::default()
+///         .with_max_entries(50_000)
+///         .with_max_bytes(Some(16 << 20)),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters, WithSetters)]
+#[getset(get_copy = "pub", set_with = "pub")]
+pub struct BufferLimits {
+  max_entries: usize,
+  /// Upper bound of the (approximate) memory used by the entries.
+  max_bytes: Option<usize>,
+}
+
+impl Default for BufferLimits {
+  fn default() -> Self {
+    Self {
+      max_entries: 3072,
+      max_bytes: None,
+    }
+  }
+}
+
+/// A ring buffer of log entries with a runtime capacity.
+///
+/// Why not `CircularBuffer`? Its capacity is a const generic, i.e. fixed at
+/// compile time, and it can not be bounded by the size of its entries.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LogBuffer {
+  entries: VecDeque<LogEntry>,
+  /// Sequence number of `entries[0]`.
+  ///
+  /// Every entry ever pushed gets a unique, increasing sequence number, so
+  /// views can tell which entries are new and which have been dropped.
+  front_seq: u64,
+  limits: BufferLimits,
+  /// Sum of `LogEntry::size_bytes` of all entries.
+  bytes: usize,
+  /// Collapse identical consecutive entries into one.
+  dedup: bool,
+}
+
+impl LogBuffer {
+  pub(crate) fn push_back(&mut self, entry: LogEntry) {
+    if let Some(last) = self
+      .entries
+      .back_mut()
+      .filter(|last| self.dedup && last.is_repeated_by(&entry))
+    {
+      last.repeat(&entry);
+      return;
+    }
+
+    self.bytes += entry.size_bytes();
+    self.entries.push_back(entry);
+    self.shrink_to_limits();
+  }
+
+  pub(crate) fn set_limits(&mut self, limits: BufferLimits) {
+    self.limits = limits;
+    self.shrink_to_limits();
+  }
+
+  pub(crate) fn set_dedup(&mut self, dedup: bool) {
+    self.dedup = dedup;
+  }
+
+  /// Drops the oldest entries until both limits are satisfied.
+  fn shrink_to_limits(&mut self) {
+    let BufferLimits {
+      max_entries,
+      max_bytes,
+    } = self.limits;
+
+    while self.entries.len() > max_entries
+      || max_bytes.is_some_and(|max| self.bytes > max)
+    {
+      match self.entries.pop_front() {
+        Some(entry) => {
+          self.bytes -= entry.size_bytes();
+          self.front_seq += 1;
+        }
+        _ => break,
+      }
+    }
+  }
+
+  /// Makes the buffer consistent again, after a thread panicked while
+  /// changing it.
+  fn repair(&mut self) {
+    self.bytes = self
+      .entries
+      .iter()
+      .map(LogEntry::size_bytes)
+      .sum();
+    self.shrink_to_limits();
+  }
+
+  /// Targets of the retained entries, sorted.
+  ///
+  /// Derived on demand (only the module filter dialog needs them), so they
+  /// neither grow without bound nor need to be counted in `bytes`.
+  pub(crate) fn targets(&self) -> BTreeSet<&CompactString> {
+    self
+      .entries
+      .iter()
+      .map(LogEntry::target)
+      .collect()
+  }
+
+  pub(crate) const fn front_seq(&self) -> u64 {
+    self.front_seq
+  }
+
+  /// Sequence number the next pushed entry will get.
+  pub(crate) fn next_seq(&self) -> u64 {
+    self.front_seq + self.entries.len() as u64
+  }
+
+  pub(crate) fn get(&self, seq: u64) -> Option<&LogEntry> {
+    seq
+      .checked_sub(self.front_seq)
+      .and_then(|i| self.entries.get(i as usize))
+  }
+
+  /// Iterates over `(entry, seq)`, starting at `seq` (or the oldest entry,
+  /// if it has already been dropped).
+  pub(crate) fn iter_from(
+    &self,
+    seq: u64,
+  ) -> impl Iterator<Item = (&LogEntry, u64)> {
+    let start = seq
+      .saturating_sub(self.front_seq)
+      .min(self.entries.len() as u64);
+
+    self
+      .entries
+      .range(start as usize..)
+      .zip(self.front_seq + start..)
+  }
+}
+
+/// A handle to a log buffer, a.k.a. a log channel.
+///
+/// Every `CursiveLogWriter` writes into one channel, and every
+/// `FlexiLoggerView` displays one channel. Both use the process-global
+/// channel (see `LogChannel::global`) unless configured otherwise, so an app
+/// can show e.g. a "network" and an "audit" pane side by side:
+///
+/// ```rust
+/// use cursive::Cursive;
+/// use cursive_logger_view::{CursiveLogWriter, FlexiLoggerView, LogChannel};
+/// use flexi_logger::Logger;
+///
+/// let siv = Cursive::default();
+/// let (network, audit) = (LogChannel::new("network"), LogChannel::new("audit"));
+///
+/// Logger::try_with_env_or_str("trace")
+///     .expect("Could not create Logger from environment :(")
+///     .log_to_writer(CursiveLogWriter::new(&siv).into_boxed())
+///     // Routes `log::info!(target: "{network}", ...)` to this writer
+///     .add_writer(
+///         "network",
+///         CursiveLogWriter::new(&siv)
+///             .with_channel(network.clone())
+///             .into_boxed(),
+///     )
+///     .add_writer(
+///         "audit",
+///         CursiveLogWriter::new(&siv)
+///             .with_channel(audit.clone())
+///             .into_boxed(),
+///     )
+///     .start()
+///     .expect("failed to initialize logger!");
+///
+/// let network_pane = FlexiLoggerView::new().with_channel(network);
+/// let audit_pane = FlexiLoggerView::new().with_channel(audit);
+/// ```
+///
+/// By default, writers append to the buffer of the channel directly, so a
+/// logging thread waits while a view renders it. In the asynchronous mode
+/// (see `set_async_queue`), they only push to a bounded lock-free queue,
+/// which the UI thread drains.
+#[derive(Clone)]
+pub struct LogChannel {
+  inner: Arc<ChannelInner>,
+}
+
+struct ChannelInner {
+  name: CompactString,
+  /// Mutex: Provides exclusive access synchronization
+  buffer: Mutex<LogBuffer>,
+  /// Set in the asynchronous mode.
+  queue: OnceLock<Queue>,
+  /// Sequence number after the entries printed by
+  /// `LogChannel::dump_to_stdout`.
+  dumped: AtomicU64,
+}
+
+impl fmt::Debug for LogChannel {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LogChannel")
+      .field("name", &self.inner.name)
+      .finish_non_exhaustive()
+  }
+}
+
+impl LogChannel {
+  /// Creates a new, empty channel with the default `BufferLimits`.
+  pub fn new(name: impl Into<CompactString>) -> Self {
+    Self {
+      inner: ChannelInner {
+        name: name.into(),
+        buffer: Default::default(),
+        queue: OnceLock::new(),
+        dumped: AtomicU64::new(0),
+      }
+      .pipe(Arc::new),
+    }
+  }
+
+  /// Marks the entries before `next_seq` as dumped, and returns where the
+  /// previous dump ended.
+  pub(crate) fn swap_dumped(&self, next_seq: u64) -> u64 {
+    self
+      .inner
+      .dumped
+      .swap(next_seq, Ordering::AcqRel)
+  }
+
+  /// The process-global channel, used by default.
+  ///
+  /// Why OnceLock? Ensures:
+  /// 1. Thread-safe lazy initialization
+  /// 2. Avoids "double initialization" race conditions
+  /// 3. No unnecessary allocation before first use
+  pub fn global() -> Self {
+    static GLOBAL: OnceLock<LogChannel> = OnceLock::new();
+
+    GLOBAL
+      .get_or_init(|| Self::new("global"))
+      .clone()
+  }
+
+  pub fn name(&self) -> &str {
+    &self.inner.name
+  }
+
+  /// Changes the limits of this channel (default: 3072 entries, no byte
+  /// limit).
+  ///
+  /// This may be called at any time. Shrinking the limits drops the oldest
+  /// entries immediately.
+  pub fn set_limits(&self, limits: BufferLimits) {
+    self.lock().set_limits(limits)
+  }
+
+  /// Chainable variant of `set_limits`.
+  pub fn with_limits(self, limits: BufferLimits) -> Self {
+    self.set_limits(limits);
+    self
+  }
+
+  /// Collapses consecutive identical records (same level, target and
+  /// message) into a single entry, rendered with a `(×N, last …)` suffix.
+  ///
+  /// Keeps a hot loop from evicting everything else from the buffer.
+  ///
+  /// ```rust
+  /// use cursive_logger_view::LogChannel;
+  ///
+  /// LogChannel::global().set_dedup(true);
+  /// ```
+  pub fn set_dedup(&self, dedup: bool) {
+    self.lock().set_dedup(dedup)
+  }
+
+  /// Chainable variant of `set_dedup`.
+  pub fn with_dedup(self, dedup: bool) -> Self {
+    self.set_dedup(dedup);
+    self
+  }
+
+  /// Switches the channel to the asynchronous mode: writers push records to
+  /// a bounded lock-free queue, which is moved into the buffer on the UI
+  /// thread (when cursive is refreshed, and whenever a view reads the
+  /// buffer).
+  ///
+  /// The mode can not be changed later, so this fails (returning `queue`) if
+  /// it was already set.
+  ///
+  /// ```rust
+  /// use cursive_logger_view::{AsyncQueue, LogChannel, OverflowPolicy};
+  ///
+  /// LogChannel::global()
+  ///     .set_async_queue(
+  ///         AsyncQueue::default()
+  ///             .with_capacity(4096)
+  ///             .with_policy(OverflowPolicy::DropOldest),
+  ///     )
+  ///     .expect("the global channel is still synchronous");
+  /// ```
+  pub fn set_async_queue(&self, queue: AsyncQueue) -> Result<(), AsyncQueue> {
+    let mut result = Err(queue);
+    self
+      .inner
+      .queue
+      .get_or_init(|| {
+        result = Ok(());
+        Queue::new(queue)
+      });
+    result
+  }
+
+  /// Chainable variant of `set_async_queue`, for a new channel.
+  pub fn with_async_queue(self, queue: AsyncQueue) -> Self {
+    // A new channel is always synchronous
+    let _ = self.set_async_queue(queue);
+    self
+  }
+
+  /// Number of records the `AsyncQueue` dropped because it was full (always
+  /// 0 in the synchronous mode).
+  pub fn dropped(&self) -> u64 {
+    self
+      .inner
+      .queue
+      .get()
+      .map_or(0, Queue::dropped)
+  }
+
+  /// Appends an entry, through the queue in the asynchronous mode.
+  pub(crate) fn push(&self, entry: LogEntry) {
+    let entry = match self.inner.queue.get() {
+      Some(queue) => match queue.push(entry) {
+        Some(entry) => entry,
+        _ => return,
+      },
+      _ => entry,
+    };
+    // Queued entries are moved first, so they stay in order
+    self.lock().push_back(entry);
+  }
+
+  /// Moves the queued entries into the buffer, if there are any.
+  pub(crate) fn drain_queue(&self) {
+    if self
+      .inner
+      .queue
+      .get()
+      .is_some_and(|queue| !queue.is_empty())
+    {
+      drop(self.lock());
+    }
+  }
+
+  /// Locks the buffer, after moving the queued entries into it.
+  ///
+  /// A poisoned lock is recovered, so a panic while the buffer was locked
+  /// (e.g. in a view that was rendering it) does not break every later log
+  /// call and draw.
+  pub(crate) fn lock(&self) -> MutexGuard<'_, LogBuffer> {
+    self
+      .inner
+      .buffer
+      .lock()
+      .unwrap_or_else(|poisoned| self.recover(poisoned))
+      .pipe(|buffer| self.drain_into(buffer))
+  }
+
+  /// Like `lock`, but gives up if the buffer is locked, e.g. by a thread
+  /// that panicked while holding it.
+  pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, LogBuffer>> {
+    let buffer = match self.inner.buffer.try_lock() {
+      Ok(buffer) => buffer,
+      Err(TryLockError::Poisoned(poisoned)) => self.recover(poisoned),
+      Err(TryLockError::WouldBlock) => return None,
+    };
+    Some(self.drain_into(buffer))
+  }
+
+  fn recover<'b>(
+    &self,
+    poisoned: PoisonError<MutexGuard<'b, LogBuffer>>,
+  ) -> MutexGuard<'b, LogBuffer> {
+    self.inner.buffer.clear_poison();
+    let mut buffer = poisoned.into_inner();
+    buffer.repair();
+    buffer
+  }
+
+  fn drain_into<'b>(
+    &self,
+    mut buffer: MutexGuard<'b, LogBuffer>,
+  ) -> MutexGuard<'b, LogBuffer> {
+    if let Some(queue) = self.inner.queue.get() {
+      queue
+        .drain()
+        .for_each(|entry| buffer.push_back(entry));
+    }
+    buffer
+  }
+}
+
+/// Changes the limits of the global channel, see `LogChannel::set_limits`.
+pub fn set_buffer_limits(limits: BufferLimits) {
+  LogChannel::global().set_limits(limits)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn buffer(limits: BufferLimits, dedup: bool) -> LogBuffer {
+    LogBuffer {
+      limits,
+      dedup,
+      ..Default::default()
+    }
+  }
+
+  fn messages(logs: &LogBuffer) -> Vec<(u64, String)> {
+    logs
+      .iter_from(0)
+      .map(|(entry, seq)| (seq, entry.message().to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn shrink_by_entries() {
+    let mut logs = buffer(BufferLimits::default().with_max_entries(2), false);
+    ["a", "b", "c"]
+      .into_iter()
+      .for_each(|m| logs.push_back(LogEntry::test("t", m)));
+
+    assert_eq!(messages(&logs), [(1, "b".into()), (2, "c".into())]);
+    assert_eq!(logs.next_seq(), 3);
+    assert_eq!(
+      logs.bytes,
+      logs
+        .entries
+        .iter()
+        .map(LogEntry::size_bytes)
+        .sum::<usize>()
+    );
+
+    logs.set_limits(BufferLimits::default().with_max_entries(1));
+    assert_eq!(messages(&logs), [(2, "c".into())]);
+  }
+
+  #[test]
+  fn shrink_by_bytes() {
+    let size = LogEntry::test("t", "a").size_bytes();
+    let mut logs = buffer(
+      BufferLimits::default().with_max_bytes(Some(size * 2 + size / 2)),
+      false,
+    );
+    ["a", "b", "c"]
+      .into_iter()
+      .for_each(|m| logs.push_back(LogEntry::test("t", m)));
+
+    assert_eq!(messages(&logs), [(1, "b".into()), (2, "c".into())]);
+    assert_eq!(logs.bytes, size * 2);
+
+    // An entry larger than the limit does not stay either
+    logs.set_limits(BufferLimits::default().with_max_bytes(Some(size - 1)));
+    assert!(messages(&logs).is_empty());
+    assert_eq!((logs.bytes, logs.front_seq()), (0, 3));
+  }
+
+  #[test]
+  fn dedup_merges_repeats() {
+    let mut logs = buffer(BufferLimits::default(), true);
+    logs.push_back(LogEntry::test("t", "a"));
+    let bytes = logs.bytes;
+    let repeat = LogEntry::test("t", "a");
+    let last_timestamp = *repeat.timestamp();
+    logs.push_back(repeat);
+
+    assert_eq!(logs.next_seq(), 1);
+    assert_eq!(logs.bytes, bytes);
+    let entry = logs.get(0).unwrap();
+    assert_eq!(*entry.repeats(), 2);
+    assert_eq!(*entry.last_timestamp(), last_timestamp);
+    assert!(entry.timestamp() <= entry.last_timestamp());
+
+    // Another target, level or message starts a new entry
+    logs.push_back(LogEntry::test("u", "a"));
+    logs.push_back(LogEntry::test("u", "b"));
+    logs.push_back(LogEntry::test("t", "a"));
+    assert_eq!(logs.next_seq(), 4);
+    assert_eq!(
+      (1..4)
+        .map(|seq| *logs.get(seq).unwrap().repeats())
+        .collect::<Vec<_>>(),
+      [1, 1, 1]
+    );
+  }
+
+  #[test]
+  fn no_dedup_keeps_repeats() {
+    let mut logs = buffer(BufferLimits::default(), false);
+    logs.push_back(LogEntry::test("t", "a"));
+    logs.push_back(LogEntry::test("t", "a"));
+
+    assert_eq!(messages(&logs), [(0, "a".into()), (1, "a".into())]);
+  }
 }
@@ -0,0 +1,373 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use compact_str::{CompactString, ToCompactString};
+use cursive_core::{
+  direction::Direction,
+  event::{Event, EventResult, Key},
+  theme::{Effect, Style},
+  utils::markup::StyledString,
+  view::{CannotFocus, View},
+  Cursive, Printer, Rect, Vec2,
+};
+use log::LevelFilter;
+use tap::Pipe;
+
+use crate::ModuleFilter;
+
+type OnChange = Arc<dyn Fn(&mut Cursive, &ModuleFilter) + Send + Sync>;
+
+/// A target path prefix, e.g. `my_app::net` for the target
+/// `my_app::net::tcp`.
+#[derive(Debug)]
+struct Node {
+  /// Empty for the root node, which stands for all targets.
+  path: CompactString,
+  depth: usize,
+  has_children: bool,
+}
+
+impl Node {
+  fn name(&self) -> &str {
+    match self.path.rsplit_once("::") {
+      _ if self.path.is_empty() => "(all targets)",
+      Some((_, name)) => name,
+      _ => &self.path,
+    }
+  }
+}
+
+/// Edits a `ModuleFilter` as a collapsible tree of targets.
+///
+/// Every `::`-separated prefix of the targets is a node with a checkbox (is
+/// the node shown at all) and a minimum level, which is inherited by its
+/// children unless they have their own.
+///
+/// - `Up` / `Down`: move the focus, `Left` / `Right`: collapse / expand
+/// - `Space`: toggle the checkbox
+/// - `1`-`5`: set the level to `Error`, `Warn`, `Info`, `Debug` or `Trace`,
+///   `0`: `Off`, `Backspace`: inherit the level of the parent again
+///
+/// ```rust
+/// use cursive_logger_view::{FlexiLoggerView, ModuleFilterView};
+///
+/// let view = FlexiLoggerView::new();
+/// ModuleFilterView::new(view.targets(), view.module_filter().clone())
+///     .with_on_change(|siv, filter| {
+///         // e.g. apply `filter` to the view
+///     });
+/// ```
+pub struct ModuleFilterView {
+  /// All nodes, in depth-first order.
+  nodes: Vec<Node>,
+  collapsed: BTreeSet<usize>,
+  /// Index of the focused node (never hidden in a collapsed node).
+  focus: usize,
+  filter: ModuleFilter,
+  on_change: Option<OnChange>,
+}
+
+impl core::fmt::Debug for ModuleFilterView {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("ModuleFilterView")
+      .field("nodes", &self.nodes)
+      .field("collapsed", &self.collapsed)
+      .field("focus", &self.focus)
+      .field("filter", &self.filter)
+      .finish_non_exhaustive()
+  }
+}
+
+impl ModuleFilterView {
+  /// Creates the tree of `targets`, plus the targets of the directives of
+  /// `filter`.
+  pub fn new<T: AsRef<str>>(
+    targets: impl IntoIterator<Item = T>,
+    filter: ModuleFilter,
+  ) -> Self {
+    // Sorting by segments keeps children right after their parent
+    let paths = targets
+      .into_iter()
+      .map(|target| {
+        target
+          .as_ref()
+          .to_compact_string()
+      })
+      .chain(
+        filter
+          .directives()
+          .keys()
+          .cloned(),
+      )
+      .flat_map(|target| {
+        let segments = target
+          .split("::")
+          .map(CompactString::from)
+          .collect::<Vec<_>>();
+        (1..=segments.len()).map(move |n| segments[..n].to_vec())
+      })
+      .filter(|segments| {
+        segments
+          .iter()
+          .any(|s| !s.is_empty())
+      })
+      .collect::<BTreeSet<_>>();
+
+    let mut nodes = core::iter::once(Node {
+      path: CompactString::default(),
+      depth: 0,
+      has_children: false,
+    })
+    .chain(
+      paths
+        .into_iter()
+        .map(|segments| Node {
+          path: segments.join("::").into(),
+          depth: segments.len(),
+          has_children: false,
+        }),
+    )
+    .collect::<Vec<_>>();
+
+    (1..nodes.len()).for_each(|i| {
+      nodes[i - 1].has_children = nodes[i].depth > nodes[i - 1].depth;
+    });
+
+    Self {
+      nodes,
+      collapsed: BTreeSet::new(),
+      focus: 0,
+      filter,
+      on_change: None,
+    }
+  }
+
+  pub fn filter(&self) -> &ModuleFilter {
+    &self.filter
+  }
+
+  /// Replaces the filter (without calling the `on_change` callback).
+  pub fn set_filter(&mut self, filter: ModuleFilter) {
+    self.filter = filter;
+  }
+
+  /// Calls `f` with the new filter after each change made by the user.
+  pub fn set_on_change<F>(&mut self, f: F)
+  where
+    F: Fn(&mut Cursive, &ModuleFilter) + Send + Sync + 'static,
+  {
+    self.on_change = Some(Arc::new(f));
+  }
+
+  pub fn with_on_change<F>(mut self, f: F) -> Self
+  where
+    F: Fn(&mut Cursive, &ModuleFilter) + Send + Sync + 'static,
+  {
+    self.set_on_change(f);
+    self
+  }
+
+  /// Indices of the nodes that are not hidden in a collapsed node.
+  fn rows(&self) -> Vec<usize> {
+    let mut hidden_below = None;
+
+    (0..self.nodes.len())
+      .filter(|&i| {
+        let depth = self.nodes[i].depth;
+        match hidden_below {
+          Some(d) if depth > d => return false,
+          _ => hidden_below = None,
+        }
+        if self.collapsed.contains(&i) {
+          hidden_below = Some(depth);
+        }
+        true
+      })
+      .collect()
+  }
+
+  fn parent(&self, node: usize) -> Option<usize> {
+    let depth = self.nodes[node].depth;
+    (0..node)
+      .rev()
+      .find(|&i| self.nodes[i].depth < depth)
+  }
+
+  fn is_checked(&self, node: &Node) -> bool {
+    self
+      .filter
+      .level_for(&node.path)
+      != Some(LevelFilter::Off)
+  }
+
+  /// Renders the node with the index `i`.
+  fn render_row(&self, i: usize) -> StyledString {
+    let node = &self.nodes[i];
+    let mut row = StyledString::plain("  ".repeat(node.depth));
+
+    let marker = match (node.has_children, self.collapsed.contains(&i)) {
+      (false, _) => "  ",
+      (_, true) => "▸ ",
+      _ => "▾ ",
+    };
+    let checkbox = match self.is_checked(node) {
+      true => "[x] ",
+      _ => "[ ] ",
+    };
+    row.append_plain(marker);
+    row.append_plain(checkbox);
+    row.append_plain(node.name());
+    row.append_plain("  ");
+
+    // Inherited levels are dimmed
+    let level = |level: LevelFilter| {
+      level
+        .as_str()
+        .to_ascii_lowercase()
+    };
+    match (
+      self
+        .filter
+        .directives()
+        .get(&node.path),
+      self
+        .filter
+        .level_for(&node.path),
+    ) {
+      (Some(&own), _) => row.append_styled(level(own), Effect::Bold),
+      (_, Some(inherited)) => {
+        row.append_styled(format!("({})", level(inherited)), Style::secondary())
+      }
+      _ => row.append_styled("(all)", Style::secondary()),
+    }
+    row
+  }
+
+  fn changed(&self) -> EventResult {
+    match &self.on_change {
+      Some(on_change) => {
+        let (on_change, filter) = (on_change.clone(), self.filter.clone());
+        EventResult::with_cb(move |siv| on_change(siv, &filter))
+      }
+      _ => EventResult::Consumed(None),
+    }
+  }
+
+  fn set_level(&mut self, level: LevelFilter) -> EventResult {
+    self.filter.set(
+      self.nodes[self.focus]
+        .path
+        .clone(),
+      level,
+    );
+    self.changed()
+  }
+}
+
+impl View for ModuleFilterView {
+  fn draw(&self, printer: &Printer<'_, '_>) {
+    self
+      .rows()
+      .into_iter()
+      .enumerate()
+      .for_each(|(y, i)| {
+        let row = self.render_row(i);
+        match i == self.focus && printer.focused {
+          true => printer.with_effect(Effect::Reverse, |p| {
+            p.print_hline((0, y), printer.size.x, " ");
+            p.print_styled((0, y), &row);
+          }),
+          _ => printer.print_styled((0, y), &row),
+        }
+      })
+  }
+
+  fn required_size(&mut self, _: Vec2) -> Vec2 {
+    let rows = self.rows();
+    let width = rows
+      .iter()
+      .map(|&i| self.render_row(i).width())
+      .max()
+      .unwrap_or(0);
+
+    Vec2::new(width, rows.len())
+  }
+
+  fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+    Ok(EventResult::Consumed(None))
+  }
+
+  fn on_event(&mut self, event: Event) -> EventResult {
+    use LevelFilter::{Debug, Error, Info, Off, Trace, Warn};
+
+    let rows = self.rows();
+    let row = rows
+      .iter()
+      .position(|&i| i == self.focus)
+      .unwrap_or(0);
+    let node = &self.nodes[self.focus];
+
+    match event {
+      Event::Key(Key::Up) if row > 0 => self.focus = rows[row - 1],
+      Event::Key(Key::Down) if row + 1 < rows.len() => self.focus = rows[row + 1],
+      Event::Key(Key::Right) if node.has_children => {
+        self
+          .collapsed
+          .remove(&self.focus);
+      }
+      Event::Key(Key::Left) => match node.has_children
+        && !self
+          .collapsed
+          .contains(&self.focus)
+      {
+        true => {
+          self
+            .collapsed
+            .insert(self.focus);
+        }
+        _ => match self.parent(self.focus) {
+          Some(parent) => self.focus = parent,
+          _ => return EventResult::Ignored,
+        },
+      },
+      Event::Char(' ') => {
+        match self.is_checked(node) {
+          true => self
+            .filter
+            .set(node.path.clone(), Off),
+          _ => {
+            let path = node.path.clone();
+            self.filter.remove(&path);
+            // Still hidden by a parent, so it needs its own level
+            if !self.is_checked(&self.nodes[self.focus]) {
+              self.filter.set(path, Trace);
+            }
+          }
+        }
+        return self.changed();
+      }
+      Event::Key(Key::Backspace | Key::Del) => {
+        let path = node.path.clone();
+        self.filter.remove(&path);
+        return self.changed();
+      }
+      Event::Char('0') => return self.set_level(Off),
+      Event::Char('1') => return self.set_level(Error),
+      Event::Char('2') => return self.set_level(Warn),
+      Event::Char('3') => return self.set_level(Info),
+      Event::Char('4') => return self.set_level(Debug),
+      Event::Char('5') => return self.set_level(Trace),
+      _ => return EventResult::Ignored,
+    }
+
+    EventResult::Consumed(None)
+  }
+
+  fn important_area(&self, view_size: Vec2) -> Rect {
+    self
+      .rows()
+      .iter()
+      .position(|&i| i == self.focus)
+      .unwrap_or(0)
+      .pipe(|y| Rect::from_size((0, y), (view_size.x, 1)))
+  }
+}
@@ -0,0 +1,149 @@
+use std::{
+  cell::Cell,
+  io::{self, IsTerminal, Write},
+  panic::{self, AssertUnwindSafe},
+  thread,
+  time::Duration,
+};
+
+use getset::{CopyGetters, Getters, WithSetters};
+
+use crate::{export, FlexiLoggerView, LogChannel, LogTheme};
+
+/// Leaves the alternate screen and undoes what TUI backends usually change:
+/// mouse tracking, hidden cursor and text attributes.
+const RESTORE_TERMINAL: &str =
+  "\x1b[0m\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l\x1b[?25h\x1b[?1049l";
+
+thread_local! {
+  /// Set while `catch_unwind_silently` runs on this thread.
+  static SILENCED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// `panic::catch_unwind`, but the hook installed by `PanicHook` ignores the
+/// panic (e.g. of a `Display` impl of a logged value), instead of printing
+/// it into the running TUI.
+pub(crate) fn catch_unwind_silently<R>(f: impl FnOnce() -> R) -> thread::Result<R> {
+  let silenced = SILENCED.replace(true);
+  let result = panic::catch_unwind(AssertUnwindSafe(f));
+  SILENCED.set(silenced);
+  result
+}
+
+/// A panic hook which prints the last buffered entries to stderr, so crash
+/// reports include the logs leading up to the panic.
+///
+/// The hook prints the entries and then calls the previous hook (which
+/// prints the panic message). Panics caught by `CursiveLogWriter` (while
+/// formatting a record) are skipped entirely, including the previous hook.
+///
+/// While cursive runs, stderr ends up on its alternate screen, so for panics
+/// of the thread that installed the hook (usually the one running cursive),
+/// the hook leaves the alternate screen first (see `restore_terminal`).
+/// Output of panics in other threads vanishes with the alternate screen,
+/// unless stderr is redirected (e.g. `2> crash.log`).
+///
+/// ```rust
+/// use cursive_logger_view::PanicHook;
+///
+/// PanicHook::default().with_entries(100).install();
+/// ```
+#[derive(Debug, Clone, Getters, CopyGetters, WithSetters)]
+pub struct PanicHook {
+  /// Number of entries to print (default: 50).
+  #[getset(get_copy = "pub", set_with = "pub")]
+  entries: usize,
+  /// The channel to print (default: `LogChannel::global()`).
+  #[getset(get = "pub", set_with = "pub")]
+  channel: LogChannel,
+  /// Color the entries with ANSI escape sequences (default: if stderr is a
+  /// terminal).
+  #[getset(get_copy = "pub", set_with = "pub")]
+  ansi: bool,
+  /// Leave the alternate screen before printing, if the panic happens on the
+  /// thread that installed the hook (default: `true`).
+  ///
+  /// Only escape sequences are written to stdout; the raw mode is left when
+  /// the panic unwinds the backend. Disable it if the app catches panics of
+  /// the thread running cursive and keeps running: cursive would keep
+  /// drawing on the normal screen otherwise.
+  #[getset(get_copy = "pub", set_with = "pub")]
+  restore_terminal: bool,
+}
+
+impl Default for PanicHook {
+  fn default() -> Self {
+    Self {
+      entries: 50,
+      channel: LogChannel::global(),
+      ansi: io::stderr().is_terminal(),
+      restore_terminal: true,
+    }
+  }
+}
+
+impl PanicHook {
+  /// Installs the hook, chained before the current one.
+  ///
+  /// `restore_terminal` applies to panics of the calling thread only.
+  pub fn install(self) {
+    let previous = panic::take_hook();
+    let installer = thread::current().id();
+
+    panic::set_hook(Box::new(move |info| {
+      if SILENCED.get() {
+        return;
+      }
+      if self.restore_terminal && thread::current().id() == installer {
+        restore_terminal();
+      }
+      let _ = self.print_entries();
+      previous(info);
+    }))
+  }
+
+  fn print_entries(&self) -> io::Result<()> {
+    // The panicking thread may hold the lock itself, so never wait for long
+    let Some(logs) = (0..10).find_map(|i| {
+      if i > 0 {
+        thread::sleep(Duration::from_millis(10));
+      }
+      self.channel.try_lock()
+    }) else {
+      return writeln!(io::stderr(), "(the log buffer is locked)");
+    };
+
+    // In raw mode, `\n` does not return the cursor to the start of the line
+    let newline = match io::stderr().is_terminal() {
+      true => "\r\n",
+      _ => "\n",
+    };
+    let view = FlexiLoggerView::new().with_theme(LogTheme::default());
+    let mut stderr = io::stderr().lock();
+
+    write!(stderr, "Last log entries:{newline}")?;
+    logs
+      .iter_from(
+        logs
+          .next_seq()
+          .saturating_sub(self.entries as u64),
+      )
+      .flat_map(|(entry, _)| view.entry_lines(entry, None))
+      .try_for_each(|line| {
+        let text = match self.ansi {
+          true => export::ansi_line(&line.text),
+          _ => export::plain_line(&line.text),
+        };
+        write!(stderr, "{}{text}{newline}", " ".repeat(line.x))
+      })?;
+    stderr.flush()
+  }
+}
+
+fn restore_terminal() {
+  let mut stdout = io::stdout();
+  if stdout.is_terminal() {
+    let _ = write!(stdout, "{RESTORE_TERMINAL}");
+    let _ = stdout.flush();
+  }
+}
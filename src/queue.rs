@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use getset::{CopyGetters, WithSetters};
+
+use crate::LogEntry;
+
+/// What the `AsyncQueue` of a `LogChannel` does with a record when it is
+/// full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+  /// Wait for the log buffer (like the synchronous mode does), move the
+  /// queued records into it and append the record.
+  #[default]
+  Block,
+  /// Drop the record.
+  DropNewest,
+  /// Drop the oldest queued record to make room.
+  DropOldest,
+}
+
+/// Configuration of the asynchronous mode of a `LogChannel`, see
+/// `LogChannel::set_async_queue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters, WithSetters)]
+#[getset(get_copy = "pub", set_with = "pub")]
+pub struct AsyncQueue {
+  /// Number of records the queue holds until the UI thread drains it.
+  capacity: usize,
+  policy: OverflowPolicy,
+}
+
+impl Default for AsyncQueue {
+  fn default() -> Self {
+    Self {
+      capacity: 1024,
+      policy: OverflowPolicy::default(),
+    }
+  }
+}
+
+/// A bounded lock-free queue between the logging threads and the buffer of
+/// a channel.
+#[derive(Debug)]
+pub(crate) struct Queue {
+  sender: Sender<LogEntry>,
+  /// Also used by the senders, to drop the oldest record.
+  receiver: Receiver<LogEntry>,
+  policy: OverflowPolicy,
+  dropped: AtomicU64,
+}
+
+impl Queue {
+  pub(crate) fn new(config: AsyncQueue) -> Self {
+    // A capacity of 0 would make every send wait for the receiver
+    let (sender, receiver) = crossbeam_channel::bounded(config.capacity.max(1));
+
+    Self {
+      sender,
+      receiver,
+      policy: config.policy,
+      dropped: AtomicU64::new(0),
+    }
+  }
+
+  /// Queues (or drops) the entry, or gives it back if the `Block` policy
+  /// needs the caller to append it to the buffer.
+  pub(crate) fn push(&self, mut entry: LogEntry) -> Option<LogEntry> {
+    loop {
+      match (self.sender.try_send(entry), self.policy) {
+        (Ok(()), _) => return None,
+        (Err(TrySendError::Full(e)), OverflowPolicy::DropOldest) => {
+          if self.receiver.try_recv().is_ok() {
+            self.count_dropped();
+          }
+          entry = e;
+        }
+        (Err(TrySendError::Full(_)), OverflowPolicy::DropNewest) => {
+          self.count_dropped();
+          return None;
+        }
+        (Err(e), _) => return Some(e.into_inner()),
+      }
+    }
+  }
+
+  fn count_dropped(&self) {
+    self
+      .dropped
+      .fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(crate) fn dropped(&self) -> u64 {
+    self
+      .dropped
+      .load(Ordering::Relaxed)
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.receiver.is_empty()
+  }
+
+  /// The queued entries, oldest first.
+  pub(crate) fn drain(&self) -> impl Iterator<Item = LogEntry> + '_ {
+    self.receiver.try_iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tap::Pipe;
+
+  use super::*;
+
+  /// A full queue of capacity 2 (`a`, `b`) which gets `c` pushed.
+  fn push_when_full(policy: OverflowPolicy) -> (Queue, Option<LogEntry>) {
+    let queue = AsyncQueue::default()
+      .with_capacity(2)
+      .with_policy(policy)
+      .pipe(Queue::new);
+    for message in ["a", "b"] {
+      assert!(queue
+        .push(LogEntry::test("t", message))
+        .is_none());
+    }
+    let rejected = queue.push(LogEntry::test("t", "c"));
+    (queue, rejected)
+  }
+
+  fn messages(queue: &Queue) -> Vec<String> {
+    queue
+      .drain()
+      .map(|entry| entry.message().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn block_gives_the_entry_back() {
+    let (queue, rejected) = push_when_full(OverflowPolicy::Block);
+
+    assert_eq!(
+      rejected
+        .as_ref()
+        .map(LogEntry::message),
+      Some(&"c".into())
+    );
+    assert_eq!(messages(&queue), ["a", "b"]);
+    assert_eq!(queue.dropped(), 0);
+  }
+
+  #[test]
+  fn drop_newest() {
+    let (queue, rejected) = push_when_full(OverflowPolicy::DropNewest);
+
+    assert!(rejected.is_none());
+    assert_eq!(messages(&queue), ["a", "b"]);
+    assert_eq!(queue.dropped(), 1);
+  }
+
+  #[test]
+  fn drop_oldest() {
+    let (queue, rejected) = push_when_full(OverflowPolicy::DropOldest);
+
+    assert!(rejected.is_none());
+    assert_eq!(messages(&queue), ["b", "c"]);
+    assert_eq!(queue.dropped(), 1);
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn zero_capacity_holds_one_entry() {
+    let queue = AsyncQueue::default()
+      .with_capacity(0)
+      .with_policy(OverflowPolicy::DropNewest)
+      .pipe(Queue::new);
+
+    assert!(queue
+      .push(LogEntry::test("t", "a"))
+      .is_none());
+    assert!(queue
+      .push(LogEntry::test("t", "b"))
+      .is_none());
+    assert_eq!(messages(&queue), ["a"]);
+    assert_eq!(queue.dropped(), 1);
+  }
+}
@@ -0,0 +1,227 @@
+use core::ops::Range;
+
+use compact_str::{format_compact, CompactString};
+use cursive_core::{
+  event::{Event, Key},
+  theme::{Effect, Style},
+  utils::markup::StyledString,
+};
+use regex::Regex;
+use tap::Pipe;
+
+/// What the view should do after the search consumed an event.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SearchEvent {
+  Ignored,
+  Consumed,
+  /// The current hit changed, so the view should scroll to it.
+  Jump,
+}
+
+/// State of the `/`-style search of the `FlexiLoggerView`.
+///
+/// - `/` starts a new (plain substring) search, `Ctrl+R` toggles regex mode
+///   while typing.
+/// - `Enter` confirms the query and jumps to the most recent hit, `Esc` clears
+///   the search.
+/// - `n` / `N` jump to the next / previous hit.
+#[derive(Debug, Default)]
+pub(crate) struct Search {
+  query: CompactString,
+  regex_mode: bool,
+  /// `None` if the query is empty or not in regex mode.
+  regex: Option<Result<Regex, regex::Error>>,
+  editing: bool,
+  /// Line numbers (of the rendered view) containing at least one match.
+  hits: Vec<usize>,
+  current: Option<usize>,
+}
+
+impl Search {
+  /// Whether matches should be highlighted at all.
+  pub(crate) fn is_active(&self) -> bool {
+    self.editing || !self.query.is_empty()
+  }
+
+  /// The query as far as it affects rendering (`None` if inactive).
+  pub(crate) fn key(&self) -> Option<(CompactString, bool)> {
+    self
+      .is_active()
+      .then(|| (self.query.clone(), self.regex_mode))
+  }
+
+  pub(crate) fn on_event(&mut self, event: &Event) -> SearchEvent {
+    if self.editing {
+      return self.on_editing_event(event);
+    }
+
+    match event {
+      Event::Char('/') => {
+        *self = Self {
+          editing: true,
+          regex_mode: self.regex_mode,
+          ..Default::default()
+        };
+      }
+      Event::Char('n') if !self.hits.is_empty() => {
+        self.current = self
+          .current
+          .map_or(0, |i| (i + 1) % self.hits.len())
+          .into();
+        return SearchEvent::Jump;
+      }
+      Event::Char('N') if !self.hits.is_empty() => {
+        self.current = self
+          .current
+          .map_or(0, |i| {
+            i.checked_sub(1)
+              .unwrap_or(self.hits.len() - 1)
+          })
+          .into();
+        return SearchEvent::Jump;
+      }
+      Event::Key(Key::Esc) if self.is_active() => *self = Self::default(),
+      _ => return SearchEvent::Ignored,
+    }
+    SearchEvent::Consumed
+  }
+
+  fn on_editing_event(&mut self, event: &Event) -> SearchEvent {
+    match event {
+      Event::Char(c) => {
+        self.query.push(*c);
+        self.compile();
+      }
+      Event::Key(Key::Backspace) => {
+        self.query.pop();
+        self.compile();
+      }
+      Event::CtrlChar('r') => {
+        self.regex_mode = !self.regex_mode;
+        self.compile();
+      }
+      Event::Key(Key::Enter) => {
+        self.editing = false;
+        self.current = self.hits.len().checked_sub(1);
+        return SearchEvent::Jump;
+      }
+      Event::Key(Key::Esc) => *self = Self::default(),
+      _ => return SearchEvent::Ignored,
+    }
+    SearchEvent::Consumed
+  }
+
+  fn compile(&mut self) {
+    self.regex =
+      (self.regex_mode && !self.query.is_empty()).then(|| Regex::new(&self.query));
+  }
+
+  /// Byte ranges of all (non-empty) matches in `text`.
+  fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+    if self.query.is_empty() {
+      return Vec::new();
+    }
+
+    match (&self.regex, self.regex_mode) {
+      (Some(Ok(re)), _) => re
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.range())
+        .collect(),
+      (_, true) => Vec::new(),
+      _ => text
+        .match_indices(self.query.as_str())
+        .map(|(i, m)| i..i + m.len())
+        .collect(),
+    }
+  }
+
+  pub(crate) fn is_match(&self, line: &StyledString) -> bool {
+    line
+      .spans()
+      .map(|span| span.content)
+      .collect::<String>()
+      .pipe_ref(|text| {
+        !self
+          .find_ranges(text)
+          .is_empty()
+      })
+  }
+
+  /// Returns a copy of `line` with all matches highlighted.
+  pub(crate) fn highlight(&self, line: &StyledString) -> StyledString {
+    let text = line
+      .spans()
+      .map(|span| span.content)
+      .collect::<String>();
+    let ranges = self.find_ranges(&text);
+
+    if ranges.is_empty() {
+      return line.clone();
+    }
+
+    let mut highlighted = StyledString::new();
+    let mut offset = 0;
+
+    for span in line.spans() {
+      let end = offset + span.content.len();
+      let mut pos = offset;
+
+      for range in ranges
+        .iter()
+        .filter(|r| r.start < end && r.end > offset)
+      {
+        let (start, stop) = (range.start.max(offset), range.end.min(end));
+        if start > pos {
+          highlighted.append_styled(&text[pos..start], *span.attr);
+        }
+        highlighted.append_styled(
+          &text[start..stop],
+          span
+            .attr
+            .combine(Style::from(Effect::Reverse)),
+        );
+        pos = stop;
+      }
+
+      if pos < end {
+        highlighted.append_styled(&text[pos..end], *span.attr);
+      }
+      offset = end;
+    }
+
+    highlighted
+  }
+
+  pub(crate) fn set_hits(&mut self, hits: Vec<usize>) {
+    self.current = self
+      .current
+      .filter(|_| !hits.is_empty())
+      .map(|i| i.min(hits.len() - 1));
+    self.hits = hits;
+  }
+
+  /// Line number of the current hit.
+  pub(crate) fn current_line(&self) -> Option<usize> {
+    self
+      .current
+      .and_then(|i| self.hits.get(i))
+      .copied()
+  }
+
+  /// Text for the status bar, e.g. `/foo  [3/17]`.
+  pub(crate) fn status(&self) -> CompactString {
+    let prefix = if self.regex_mode { "regex /" } else { "/" };
+    let query = &self.query;
+
+    match (&self.regex, self.editing, self.current) {
+      (Some(Err(_)), ..) => format_compact!("{prefix}{query}  [invalid regex]"),
+      (_, true, _) => format_compact!("{prefix}{query}"),
+      _ if self.hits.is_empty() => format_compact!("{prefix}{query}  [no matches]"),
+      (.., Some(i)) => {
+        format_compact!("{prefix}{query}  [{}/{}]", i + 1, self.hits.len())
+      }
+      _ => format_compact!("{prefix}{query}  [{} matches]", self.hits.len()),
+    }
+  }
+}
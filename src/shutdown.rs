@@ -0,0 +1,256 @@
+use core::fmt;
+use std::{
+  io::{self, Write},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, PoisonError,
+  },
+};
+
+use flexi_logger::{DeferredNow, Record};
+use tap::Pipe;
+
+use crate::{export, CursiveLogWriter, FlexiLoggerView, LogChannel};
+
+/// What a `CursiveLogWriter` does with records once cursive has exited, i.e.
+/// its `Cursive` instance (and so the callback sink) was dropped.
+///
+/// The exit is noticed by the first record written after it, or when the
+/// logger shuts down. Mind the drop order: the `flexi_logger::LoggerHandle`
+/// is usually created after the `Cursive` instance, and so dropped before
+/// it, while the sink is still open.
+///
+/// ```rust
+/// use std::io;
+///
+/// use cursive::Cursive;
+/// use cursive_logger_view::{CursiveLogWriter, ShutdownPolicy};
+///
+/// let siv = Cursive::default();
+/// CursiveLogWriter::new(&siv)
+///     .with_shutdown_policy(ShutdownPolicy::forward(io::stderr()));
+/// ```
+#[derive(Default)]
+pub enum ShutdownPolicy {
+  /// Keep storing the records in the channel, without reporting an error.
+  #[default]
+  Buffer,
+  /// Write the records to a fallback writer (e.g. stderr or a file), as
+  /// plain text lines in the default format of `FlexiLoggerView`.
+  Forward(Mutex<Box<dyn Write + Send>>),
+  /// Print the entries retained in the channel to stdout, followed by every
+  /// new record.
+  ///
+  /// The entries are printed with the first record after the exit, or when
+  /// the logger shuts down (`flexi_logger::LoggerHandle` is dropped) after
+  /// the `Cursive` instance. As the handle is usually dropped first, call
+  /// `LogChannel::dump_to_stdout` once `siv.run()` returns; entries are
+  /// printed only once either way.
+  DumpToStdout,
+  /// Keep storing the records, but fail every write with a `BrokenPipe`
+  /// error (which `flexi_logger` reports on stderr).
+  Fail,
+}
+
+impl ShutdownPolicy {
+  /// `ShutdownPolicy::Forward` to `writer`.
+  pub fn forward(writer: impl Write + Send + 'static) -> Self {
+    Self::Forward(Mutex::new(Box::new(writer)))
+  }
+}
+
+impl fmt::Debug for ShutdownPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Buffer => write!(f, "Buffer"),
+      Self::Forward(_) => write!(f, "Forward(..)"),
+      Self::DumpToStdout => write!(f, "DumpToStdout"),
+      Self::Fail => write!(f, "Fail"),
+    }
+  }
+}
+
+/// Clears the `refresh_pending` flag of a `CursiveLogWriter` when the
+/// refresh callback is dropped, i.e. after it ran, or when cursive exited
+/// without running it (otherwise the writer would never send another one,
+/// and so never notice the exit).
+pub(crate) struct PendingRefresh(pub(crate) Arc<AtomicBool>);
+
+impl Drop for PendingRefresh {
+  fn drop(&mut self) {
+    self
+      .0
+      .store(false, Ordering::Release)
+  }
+}
+
+impl CursiveLogWriter {
+  pub(crate) fn is_shut_down(&self) -> bool {
+    self
+      .shut_down
+      .load(Ordering::Acquire)
+  }
+
+  /// Handles a record logged after cursive has exited. The `first` one was
+  /// already stored in the channel before the exit was noticed.
+  pub(crate) fn write_after_shutdown(
+    &self,
+    now: &mut DeferredNow,
+    record: &Record,
+    first: bool,
+  ) -> io::Result<()> {
+    let line = |now: &mut DeferredNow| {
+      Self::entry(now, record).map(|entry| {
+        FlexiLoggerView::new()
+          .render_entry(&entry)
+          .0
+          .pipe_ref(export::plain_line)
+      })
+    };
+
+    match &self.shutdown_policy {
+      ShutdownPolicy::Buffer | ShutdownPolicy::Fail if !first => self
+        .channel
+        .push(Self::entry(now, record)?),
+      _ => {}
+    }
+
+    match &self.shutdown_policy {
+      ShutdownPolicy::Buffer => Ok(()),
+      ShutdownPolicy::Forward(writer) => {
+        let line = line(now)?;
+        writeln!(
+          writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+          "{line}"
+        )
+      }
+      ShutdownPolicy::DumpToStdout if first => self.dump_to_stdout(),
+      ShutdownPolicy::DumpToStdout => {
+        writeln!(io::stdout().lock(), "{}", line(now)?)
+      }
+      ShutdownPolicy::Fail => Err(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "cursive callback sink is closed!",
+      )),
+    }
+  }
+
+  /// Prints the entries of the channel, once cursive has exited.
+  fn dump_to_stdout(&self) -> io::Result<()> {
+    self.channel.dump_to_stdout()
+  }
+
+  /// Called when the logger shuts down: dumps the channel if cursive has
+  /// exited, but nothing was logged since.
+  pub(crate) fn dump_on_shutdown(&self) {
+    if !matches!(self.shutdown_policy, ShutdownPolicy::DumpToStdout)
+      || self.is_shut_down()
+    {
+      return;
+    }
+
+    // A no-op callback only fails to send if cursive is gone
+    if self
+      .sink
+      .send(Box::new(|_| {}))
+      .is_err()
+    {
+      self
+        .shut_down
+        .store(true, Ordering::Release);
+      let _ = self.dump_to_stdout();
+    }
+  }
+}
+
+impl LogChannel {
+  /// Prints the entries of this channel to stdout, as plain text lines in the
+  /// default format of `FlexiLoggerView`.
+  ///
+  /// Meant to be called once cursive has exited, see
+  /// `ShutdownPolicy::DumpToStdout`. Entries that were already printed (by
+  /// an earlier call, or by the policy) are skipped.
+  ///
+  /// ```rust
+  /// use cursive::{Cursive, CursiveExt};
+  /// use cursive_logger_view::{CursiveLogWriter, LogChannel, ShutdownPolicy};
+  /// use flexi_logger::Logger;
+  ///
+  /// let mut siv = Cursive::default();
+  ///
+  /// let _handle = Logger::try_with_env_or_str("info")
+  ///     .expect("Could not create Logger from environment :(")
+  ///     .log_to_writer(
+  ///         CursiveLogWriter::new(&siv)
+  ///             .with_shutdown_policy(ShutdownPolicy::DumpToStdout)
+  ///             .into_boxed(),
+  ///     )
+  ///     .start()
+  ///     .expect("failed to initialize logger!");
+  ///
+  /// // siv.run();
+  /// LogChannel::global().dump_to_stdout()?;
+  /// # Ok::<(), std::io::Error>(())
+  /// ```
+  pub fn dump_to_stdout(&self) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    self.dump_to(&mut stdout)?;
+    stdout.flush()
+  }
+
+  fn dump_to(&self, writer: &mut impl Write) -> io::Result<()> {
+    let view = FlexiLoggerView::new();
+    let logs = self.lock();
+    let from = self.swap_dumped(logs.next_seq());
+
+    logs
+      .iter_from(from)
+      .try_for_each(|(entry, _)| {
+        view
+          .render_entry(entry)
+          .0
+          .pipe_ref(export::plain_line)
+          .pipe(|line| writeln!(writer, "{line}"))
+      })?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::LogEntry;
+
+  fn dump(channel: &LogChannel) -> Vec<String> {
+    let mut out = Vec::new();
+    channel
+      .dump_to(&mut out)
+      .unwrap();
+    String::from_utf8(out)
+      .unwrap()
+      .lines()
+      .map(|line| {
+        line
+          .rsplit(' ')
+          .next()
+          .unwrap_or_default()
+          .to_owned()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn entries_are_dumped_once() {
+    let channel = LogChannel::new("dump");
+    channel.push(LogEntry::test("t", "a"));
+    channel.push(LogEntry::test("t", "b"));
+
+    assert_eq!(dump(&channel), ["a", "b"]);
+    assert!(dump(&channel).is_empty());
+
+    channel.push(LogEntry::test("t", "c"));
+    assert_eq!(dump(&channel), ["c"]);
+  }
+}
@@ -0,0 +1,289 @@
+use cursive_core::{event::Event, theme::Effect, utils::markup::StyledString};
+
+use tap::Pipe;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{FlexiLoggerView, FormatItem, LogEntry, LogItems};
+
+const SEPARATOR: &str = " │ ";
+
+/// Column state of the table mode of the `FlexiLoggerView`.
+///
+/// Columns are numbered by their position among the field items of the
+/// format (literal `Text` and `Custom` items are not columns).
+///
+/// - `<` / `>` select the previous / next column.
+/// - `+` / `-` widen / narrow the selected column, `=` resets all widths.
+/// - `h` hides the selected column, `H` shows all columns again.
+#[derive(Debug, Default)]
+pub(crate) struct Columns {
+  selected: usize,
+  hidden: Vec<usize>,
+  /// Width changes (by column) relative to the auto-sized widths.
+  resized: Vec<isize>,
+}
+
+impl Columns {
+  pub(crate) fn hidden(&self) -> &[usize] {
+    &self.hidden
+  }
+
+  fn is_visible(&self, column: usize) -> bool {
+    !self.hidden.contains(&column)
+  }
+
+  /// Handles the table keys, for a table of `count` columns.
+  pub(crate) fn on_event(&mut self, event: &Event, count: usize) -> bool {
+    let visible = (0..count)
+      .filter(|&c| self.is_visible(c))
+      .collect::<Vec<_>>();
+    let position = visible
+      .iter()
+      .position(|&c| c == self.selected);
+    let neighbour = |offset: isize| {
+      position
+        .and_then(|p| p.checked_add_signed(offset))
+        .and_then(|p| visible.get(p))
+        .or(visible.first())
+        .copied()
+    };
+
+    match event {
+      Event::Char('<') => self.selected = neighbour(-1).unwrap_or(0),
+      Event::Char('>') => self.selected = neighbour(1).unwrap_or(0),
+      Event::Char(c @ ('+' | '-')) if position.is_some() => {
+        if self.resized.len() <= self.selected {
+          self
+            .resized
+            .resize(self.selected + 1, 0);
+        }
+        self.resized[self.selected] += match c {
+          '+' => 1,
+          _ => -1,
+        };
+      }
+      Event::Char('=') => self.resized.clear(),
+      // Keep at least one column
+      Event::Char('h') if position.is_some() && visible.len() > 1 => {
+        let next = neighbour(1)
+          .filter(|&c| c != self.selected)
+          .or_else(|| neighbour(-1));
+        self.hidden.push(self.selected);
+        self.selected = next.unwrap_or(0);
+      }
+      Event::Char('H') => self.hidden.clear(),
+      _ => return false,
+    }
+    true
+  }
+}
+
+/// Whether the item is a column of the table.
+fn is_column(item: &FormatItem) -> bool {
+  !matches!(item.item(), LogItems::Text(_) | LogItems::Custom(_))
+}
+
+const fn header_name(item: &LogItems) -> &'static str {
+  use crate::LogItems::*;
+
+  match item {
+    DateTime => "TIME",
+    Thread => "THREAD",
+    Level => "LEVEL",
+    Target => "TARGET",
+    ModLine => "MODULE",
+    File | FileLine => "FILE",
+    KeyValues => "FIELDS",
+    Message => "MESSAGE",
+    Custom(_) | Text(_) => "",
+  }
+}
+
+/// Width of a row before its last cell, with the cells `widths` wide.
+pub(crate) fn prefix_width(widths: &[usize]) -> usize {
+  widths
+    .split_last()
+    .map_or(0, |(_, widths)| {
+      widths.iter().sum::<usize>() + widths.len() * SEPARATOR.width()
+    })
+}
+
+/// The first line of a cell (only the last column can have several).
+fn first_line(cell: StyledString) -> StyledString {
+  if !cell.source().contains('\n') {
+    return cell;
+  }
+
+  let mut line = StyledString::new();
+  for span in cell.spans() {
+    match span.content.split_once('\n') {
+      Some((content, _)) => {
+        line.append_styled(content, *span.attr);
+        break;
+      }
+      _ => line.append_styled(span.content, *span.attr),
+    }
+  }
+  line
+}
+
+impl FlexiLoggerView {
+  /// The visible columns, as `(column, item)`.
+  fn columns(&self) -> impl Iterator<Item = (usize, &FormatItem)> {
+    self
+      .format
+      .iter()
+      .filter(|item| is_column(item))
+      .enumerate()
+      .filter(|(column, _)| self.columns.is_visible(*column))
+  }
+
+  pub(crate) fn column_count(&self) -> usize {
+    self
+      .format
+      .iter()
+      .filter(|item| is_column(item))
+      .count()
+  }
+
+  /// Rendered values of the visible columns of an entry.
+  fn cells(&self, entry: &LogEntry) -> Vec<StyledString> {
+    let level_style = self.theme.level(*entry.level());
+
+    self
+      .columns()
+      .map(|(_, item)| {
+        let style = |theme| {
+          item
+            .style()
+            .resolve(theme, level_style)
+        };
+        item.fit(
+          self
+            .render_item(item.item(), entry, style)
+            .0,
+        )
+      })
+      .collect()
+  }
+
+  /// Renders an entry as a table row, with each column cut and padded to its
+  /// width in `widths` (or at its natural width, if `None`).
+  ///
+  /// The last column is never cut, so long (and multi-line) messages stay
+  /// readable.
+  pub(crate) fn render_row(
+    &self,
+    entry: &LogEntry,
+    widths: Option<&[usize]>,
+  ) -> StyledString {
+    let cells = self.cells(entry);
+    let last = cells.len().saturating_sub(1);
+
+    self
+      .columns()
+      .zip(cells)
+      .enumerate()
+      .fold(StyledString::new(), |mut row, (i, ((_, item), cell))| {
+        if i > 0 {
+          row.append_plain(SEPARATOR);
+        }
+        match (i == last, widths.and_then(|w| w.get(i))) {
+          (true, _) => row.append(cell),
+          (_, Some(&width)) => item
+            .clone()
+            .with_width(Some(width))
+            .with_max_width(Some(width))
+            .with_ellipsis(true)
+            .fit(first_line(cell))
+            .pipe(|cell| row.append(cell)),
+          _ => row.append(first_line(cell)),
+        }
+        row
+      })
+  }
+
+  /// Widths of the (first lines of the) cells of an entry.
+  pub(crate) fn cell_widths(&self, entry: &LogEntry) -> Vec<usize> {
+    self
+      .cells(entry)
+      .into_iter()
+      .map(|cell| first_line(cell).width())
+      .collect()
+  }
+
+  /// Widths of the visible columns: the widest cell of `entries` (or the
+  /// header), changed by the user's resizing.
+  pub(crate) fn column_widths<'e>(
+    &self,
+    entries: impl Iterator<Item = &'e LogEntry>,
+  ) -> Vec<usize> {
+    let cell_widths = entries
+      .map(|entry| self.cell_widths(entry))
+      .collect::<Vec<_>>();
+
+    self.fit_columns(
+      cell_widths
+        .iter()
+        .map(Vec::as_slice),
+    )
+  }
+
+  /// `column_widths`, from the `cell_widths` of the entries.
+  pub(crate) fn fit_columns<'c>(
+    &self,
+    cell_widths: impl Iterator<Item = &'c [usize]>,
+  ) -> Vec<usize> {
+    let headers = self
+      .columns()
+      .map(|(_, item)| header_name(item.item()).len())
+      .collect::<Vec<_>>();
+
+    cell_widths
+      .fold(headers, |mut widths, cells| {
+        widths
+          .iter_mut()
+          .zip(cells)
+          .for_each(|(width, &cell)| *width = (*width).max(cell));
+        widths
+      })
+      .into_iter()
+      .zip(self.columns())
+      .map(|(width, (column, _))| {
+        let resized = self
+          .columns
+          .resized
+          .get(column)
+          .copied()
+          .unwrap_or(0);
+        width
+          .saturating_add_signed(resized)
+          .max(1)
+      })
+      .collect()
+  }
+
+  /// The header row, with the selected column highlighted.
+  pub(crate) fn render_header(&self, widths: &[usize]) -> StyledString {
+    self
+      .columns()
+      .zip(widths)
+      .enumerate()
+      .fold(
+        StyledString::new(),
+        |mut header, (i, ((column, item), &width))| {
+          if i > 0 {
+            header.append_plain(SEPARATOR);
+          }
+          let effect = match column == self.columns.selected {
+            true => Effect::Reverse,
+            _ => Effect::Bold,
+          };
+          let name =
+            &header_name(item.item())[..width.min(header_name(item.item()).len())];
+          header.append_styled(format!("{name:width$}"), effect);
+          header
+        },
+      )
+  }
+}
@@ -0,0 +1,348 @@
+use core::{fmt, iter::Peekable, str::CharIndices};
+
+use compact_str::CompactString;
+use cursive_core::align::HAlign;
+use tap::Pipe;
+use tinyvec::TinyVec;
+
+use crate::{FormatItem, LogItems, Truncate};
+
+/// Error of `parse_format`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatParseError {
+  /// Char offset in the template where the error was found.
+  pub position: usize,
+  pub kind: FormatParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatParseErrorKind {
+  /// A `{` without matching `}`.
+  UnclosedField,
+  /// A `}` that does not close a field (use `}}` for a literal `}`).
+  UnmatchedBrace,
+  UnknownField(CompactString),
+  /// The part after `:` is not `[<^>][width][.[-]max_width][…]`.
+  InvalidSpec(CompactString),
+}
+
+impl fmt::Display for FormatParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    use FormatParseErrorKind::*;
+
+    match &self.kind {
+      UnclosedField => write!(f, "unclosed `{{`"),
+      UnmatchedBrace => write!(f, "unmatched `}}` (use `}}}}` for a literal `}}`)"),
+      UnknownField(name) => write!(f, "unknown field `{name}`"),
+      InvalidSpec(spec) => write!(f, "invalid format spec `{spec}`"),
+    }?;
+    write!(f, " at position {}", self.position)
+  }
+}
+
+impl std::error::Error for FormatParseError {}
+
+/// Parses a format template like `"{time} [{level:>5}] {target}: {message}"`
+/// into format items.
+///
+/// Fields are written as `{name}` or `{name:spec}`, everything else is
+/// literal text (`{{` and `}}` escape braces). Fields are rendered without
+/// the brackets and the trailing space they have in `FlexiLoggerView::new`.
+///
+/// | Name                | Item                     |
+/// |---------------------|--------------------------|
+/// | `time`, `date_time` | `LogItems::DateTime`     |
+/// | `thread`            | `LogItems::Thread`       |
+/// | `level`             | `LogItems::Level`        |
+/// | `target`            | `LogItems::Target`       |
+/// | `mod_line`          | `LogItems::ModLine`      |
+/// | `file`              | `LogItems::File`         |
+/// | `file_line`         | `LogItems::FileLine`     |
+/// | `kv`, `key_values`  | `LogItems::KeyValues`    |
+/// | `message`           | `LogItems::Message`      |
+///
+/// The spec is `[align][width][.max_width]`, like in `format!`: `<`, `^` or
+/// `>` aligns the value left (default), centered or right when it is padded
+/// to `width`, and values longer than `max_width` are cut. A `-` before
+/// `max_width` keeps the end of the value instead of its start, and a
+/// trailing `…` marks cut values (see `FormatItem`).
+///
+/// ```rust
+/// use cursive_logger_view::{parse_format, FlexiLoggerView};
+///
+/// let view = FlexiLoggerView::new()
+///     .with_format_str("{time} [{level:>5}] {mod_line:30.-30…} {message}")
+///     .expect("valid template");
+///
+/// let err = parse_format("{time} {lvl}").unwrap_err();
+/// assert_eq!(err.position, 8);
+/// assert_eq!(err.to_string(), "unknown field `lvl` at position 8");
+/// ```
+pub fn parse_format(
+  template: &str,
+) -> Result<TinyVec<[FormatItem; 8]>, FormatParseError> {
+  let mut parser = Parser {
+    chars: template
+      .char_indices()
+      .peekable(),
+    template,
+    position: 0,
+  };
+  let mut items = TinyVec::new();
+  let mut text = CompactString::default();
+
+  let error = |position, kind| FormatParseError { position, kind };
+
+  while let Some(c) = parser.next() {
+    match c {
+      '{' if parser.eat('{') => text.push('{'),
+      '}' if parser.eat('}') => text.push('}'),
+      '}' => Err(error(
+        parser.position - 1,
+        FormatParseErrorKind::UnmatchedBrace,
+      ))?,
+      '{' => {
+        if !text.is_empty() {
+          items.push(bare(LogItems::Text(core::mem::take(&mut text))));
+        }
+        items.push(parser.field()?);
+      }
+      c => text.push(c),
+    }
+  }
+
+  if !text.is_empty() {
+    items.push(bare(LogItems::Text(text)));
+  }
+
+  Ok(items)
+}
+
+/// Template items are not decorated, the template has its own brackets.
+fn bare(item: LogItems<'static>) -> FormatItem {
+  FormatItem::from(item).with_decorated(false)
+}
+
+struct Parser<'t> {
+  chars: Peekable<CharIndices<'t>>,
+  template: &'t str,
+  /// Char offset of the next char.
+  position: usize,
+}
+
+impl Parser<'_> {
+  fn next(&mut self) -> Option<char> {
+    let (_, c) = self.chars.next()?;
+    self.position += 1;
+    Some(c)
+  }
+
+  /// Consumes the next char if it is `c`.
+  fn eat(&mut self, c: char) -> bool {
+    match self.chars.peek() {
+      Some(&(_, next)) if next == c => self.next().is_some(),
+      _ => false,
+    }
+  }
+
+  /// Byte offset of the next char.
+  fn offset(&mut self) -> usize {
+    self
+      .chars
+      .peek()
+      .map_or(self.template.len(), |&(i, _)| i)
+  }
+
+  /// Parses a field, after its `{`.
+  fn field(&mut self) -> Result<FormatItem, FormatParseError> {
+    let (start, start_offset) = (self.position, self.offset());
+    let error = |position, kind| FormatParseError { position, kind };
+
+    let mut colon = None;
+    let end_offset = loop {
+      let offset = self.offset();
+      match self.next() {
+        Some('}') => break offset,
+        Some(':') if colon.is_none() => colon = Some((self.position, offset)),
+        Some(_) => {}
+        None => Err(error(start - 1, FormatParseErrorKind::UnclosedField))?,
+      }
+    };
+
+    let (name, spec) = match colon {
+      Some((spec_position, offset)) => (
+        &self.template[start_offset..offset],
+        Some((spec_position, &self.template[offset + 1..end_offset])),
+      ),
+      _ => (&self.template[start_offset..end_offset], None),
+    };
+
+    let item = match name.trim() {
+      "time" | "date_time" => LogItems::DateTime,
+      "thread" => LogItems::Thread,
+      "level" => LogItems::Level,
+      "target" => LogItems::Target,
+      "mod_line" => LogItems::ModLine,
+      "file" => LogItems::File,
+      "file_line" => LogItems::FileLine,
+      "kv" | "key_values" => LogItems::KeyValues,
+      "message" => LogItems::Message,
+      _ => Err(error(
+        start,
+        FormatParseErrorKind::UnknownField(name.into()),
+      ))?,
+    }
+    .pipe(bare);
+
+    match spec {
+      Some((position, spec)) => parse_spec(item, spec).ok_or_else(|| {
+        error(position, FormatParseErrorKind::InvalidSpec(spec.into()))
+      }),
+      _ => Ok(item),
+    }
+  }
+}
+
+/// Applies `[<^>][width][.[-]max_width][…]` to `item`.
+fn parse_spec(item: FormatItem, spec: &str) -> Option<FormatItem> {
+  let (align, rest) = match spec.chars().next() {
+    Some('<') => (HAlign::Left, &spec[1..]),
+    Some('^') => (HAlign::Center, &spec[1..]),
+    Some('>') => (HAlign::Right, &spec[1..]),
+    _ => (HAlign::Left, spec),
+  };
+  let (rest, ellipsis) = match rest.strip_suffix('…') {
+    Some(rest) => (rest, true),
+    _ => (rest, false),
+  };
+  let (width, max_width, truncate) = match rest.split_once('.') {
+    Some((width, max)) => match max.strip_prefix('-') {
+      Some(max) => (width, Some(max.parse().ok()?), Truncate::Start),
+      _ => (width, Some(max.parse().ok()?), Truncate::End),
+    },
+    _ => (rest, None, Truncate::End),
+  };
+  let width = match width {
+    "" => None,
+    width => Some(width.parse().ok()?),
+  };
+
+  item
+    .with_align(align)
+    .with_width(width)
+    .with_max_width(max_width)
+    .with_truncate(truncate)
+    .with_ellipsis(ellipsis)
+    .pipe(Some)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn error(template: &str) -> (usize, FormatParseErrorKind) {
+    let err = parse_format(template).unwrap_err();
+    (err.position, err.kind)
+  }
+
+  fn field(spec: &str) -> FormatItem {
+    match parse_format(&format!("{{level:{spec}}}"))
+      .unwrap()
+      .as_slice()
+    {
+      [item] => item.clone(),
+      items => panic!("expected one item, got {items:?}"),
+    }
+  }
+
+  #[test]
+  fn literal_text_and_escapes() {
+    assert_eq!(
+      parse_format("{{a}} {message}}}")
+        .unwrap()
+        .as_slice(),
+      [
+        bare(LogItems::Text("{a} ".into())),
+        bare(LogItems::Message),
+        bare(LogItems::Text("}".into())),
+      ]
+    );
+    assert!(parse_format("")
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn field_names() {
+    let items = parse_format("{time}{date_time}{ level }{kv}{key_values}").unwrap();
+    let items = items
+      .iter()
+      .map(|item| item.item().clone())
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      items,
+      [
+        LogItems::DateTime,
+        LogItems::DateTime,
+        LogItems::Level,
+        LogItems::KeyValues,
+        LogItems::KeyValues,
+      ]
+    );
+    assert!(parse_format("{time}")
+      .unwrap()
+      .iter()
+      .all(|item| !item.decorated()));
+  }
+
+  #[test]
+  fn error_positions() {
+    use FormatParseErrorKind::*;
+
+    assert_eq!(error("ab {time"), (3, UnclosedField));
+    assert_eq!(error("ab}c"), (2, UnmatchedBrace));
+    assert_eq!(error("{time}}"), (6, UnmatchedBrace));
+    assert_eq!(error("{lvl}"), (1, UnknownField("lvl".into())));
+    // Positions count chars, not bytes
+    assert_eq!(error("é→{time:x}"), (8, InvalidSpec("x".into())));
+    assert_eq!(error("é→{lvl}"), (3, UnknownField("lvl".into())));
+  }
+
+  #[test]
+  fn specs() {
+    let item = |align, width, max_width, truncate, ellipsis| {
+      bare(LogItems::Level)
+        .with_align(align)
+        .with_width(width)
+        .with_max_width(max_width)
+        .with_truncate(truncate)
+        .with_ellipsis(ellipsis)
+    };
+    use HAlign::{Center, Left, Right};
+    use Truncate::{End, Start};
+
+    assert_eq!(field(""), item(Left, None, None, End, false));
+    assert_eq!(field("5"), item(Left, Some(5), None, End, false));
+    assert_eq!(field("<5"), item(Left, Some(5), None, End, false));
+    assert_eq!(field("^5"), item(Center, Some(5), None, End, false));
+    assert_eq!(field(">5"), item(Right, Some(5), None, End, false));
+    assert_eq!(field(".3"), item(Left, None, Some(3), End, false));
+    assert_eq!(field(".-3"), item(Left, None, Some(3), Start, false));
+    assert_eq!(field("…"), item(Left, None, None, End, true));
+    assert_eq!(field(">8.4…"), item(Right, Some(8), Some(4), End, true));
+    assert_eq!(field("^8.-4…"), item(Center, Some(8), Some(4), Start, true));
+    assert_eq!(field("8.-4"), item(Left, Some(8), Some(4), Start, false));
+  }
+
+  #[test]
+  fn invalid_specs() {
+    for spec in ["x", ".", ".-", "5.", ">>5", "5…5", "-5", "5.x"] {
+      assert_eq!(
+        error(&format!("{{level:{spec}}}")),
+        (7, FormatParseErrorKind::InvalidSpec(spec.into())),
+        "spec `{spec}`"
+      );
+    }
+  }
+}
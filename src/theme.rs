@@ -0,0 +1,152 @@
+use cursive_core::theme::{BaseColor, Color, Effect, PaletteColor, Style, Theme};
+use getset::{CopyGetters, WithSetters};
+use log::Level;
+
+/// Styles of the `FlexiLoggerView`, applied when entries are rendered.
+///
+/// The level styles are used for the `Level`, `Message` and `Custom` items,
+/// the other styles for their respective `LogItems`. Changing the theme of a
+/// view also re-styles the existing history.
+///
+/// ```rust
+/// use cursive::theme::{BaseColor, Effect, Style};
+/// use cursive_logger_view::{FlexiLoggerView, LogTheme};
+///
+/// let theme = LogTheme::bright()
+///     .with_error(Style::from(BaseColor::Red.light()).combine(Effect::Bold))
+///     .with_line_number(Style::none());
+///
+/// FlexiLoggerView::new().with_theme(theme);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters, WithSetters)]
+#[getset(get_copy = "pub", set_with = "pub")]
+pub struct LogTheme {
+  error: Style,
+  warn: Style,
+  info: Style,
+  debug: Style,
+  trace: Style,
+  date_time: Style,
+  thread: Style,
+  /// Module path and file name of `ModLine`, `File` and `FileLine`.
+  module: Style,
+  /// Line number of `ModLine`.
+  line_number: Style,
+  /// Keys of `KeyValues`.
+  key: Style,
+  /// Values of `KeyValues`.
+  value: Style,
+}
+
+impl Default for LogTheme {
+  fn default() -> Self {
+    Self::dark()
+  }
+}
+
+impl LogTheme {
+  /// Dark colors, readable on light backgrounds (the default).
+  pub fn dark() -> Self {
+    use BaseColor::{Blue, Cyan, Green, Magenta, Red, Yellow};
+
+    Self::from_level_colors([Red, Yellow, Green, Cyan, Magenta].map(Color::Dark))
+      .with_line_number(Color::Dark(Blue).into())
+      .with_key(Color::Dark(Cyan).into())
+  }
+
+  /// Light colors, readable on dark backgrounds.
+  pub fn bright() -> Self {
+    use BaseColor::{Blue, Cyan, Green, Magenta, Red, Yellow};
+
+    Self::from_level_colors([Red, Yellow, Green, Cyan, Magenta].map(Color::Light))
+      .with_line_number(Color::Light(Blue).into())
+      .with_key(Color::Light(Cyan).into())
+  }
+
+  /// No colors, levels are told apart by text effects only.
+  pub fn monochrome() -> Self {
+    Self {
+      error: Style::from(Effect::Bold).combine(Effect::Reverse),
+      warn: Effect::Bold.into(),
+      info: Style::none(),
+      debug: Effect::Dim.into(),
+      trace: Style::from(Effect::Dim).combine(Effect::Italic),
+      date_time: Style::none(),
+      thread: Style::none(),
+      module: Style::none(),
+      line_number: Effect::Underline.into(),
+      key: Effect::Underline.into(),
+      value: Effect::Bold.into(),
+    }
+  }
+
+  /// `dark` or `bright`, whichever matches the view background of `theme`.
+  ///
+  /// The other palette colors are not used: e.g. in the default palette of
+  /// cursive, `Tertiary` is white (on a white background) and `TitlePrimary`
+  /// is the red of `Error`.
+  pub fn from_cursive_theme(theme: &Theme) -> Self {
+    match is_dark(theme.palette[PaletteColor::View]) {
+      true => Self::bright(),
+      _ => Self::dark(),
+    }
+  }
+
+  /// The style of a log level.
+  pub const fn level(&self, level: Level) -> Style {
+    match level {
+      Level::Error => self.error,
+      Level::Warn => self.warn,
+      Level::Info => self.info,
+      Level::Debug => self.debug,
+      Level::Trace => self.trace,
+    }
+  }
+
+  /// Level colors from `Error` to `Trace`, the rest as in `monochrome`.
+  fn from_level_colors([error, warn, info, debug, trace]: [Color; 5]) -> Self {
+    Self {
+      error: error.into(),
+      warn: warn.into(),
+      info: info.into(),
+      debug: debug.into(),
+      trace: trace.into(),
+      ..Self::monochrome()
+    }
+  }
+}
+
+/// Whether text on this background needs light colors.
+///
+/// The terminal default is assumed to be dark.
+fn is_dark(background: Color) -> bool {
+  use BaseColor::{Black, Blue, Magenta, Red};
+
+  match background {
+    Color::TerminalDefault => true,
+    Color::Dark(c) => matches!(c, Black | Blue | Red | Magenta),
+    Color::Light(c) => c == Black,
+    Color::Rgb(r, g, b) => {
+      // Rec. 601 luma
+      299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) < 128_000
+    }
+    Color::RgbLowRes(r, g, b) => r + g + b < 8,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cursive_themes_only_pick_the_preset() {
+    assert_eq!(
+      LogTheme::from_cursive_theme(&Theme::retro()),
+      LogTheme::dark()
+    );
+    assert_eq!(
+      LogTheme::from_cursive_theme(&Theme::terminal_default()),
+      LogTheme::bright()
+    );
+  }
+}
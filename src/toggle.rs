@@ -9,11 +9,48 @@
 //!  - `hide_flexi_logger_debug_console`: hide debug console view (if visible);
 //!  - `toggle_flexi_logger_debug_console`: show the debug console view, or hide
 //!    it if it's already visible.
+//!
+//! Inside the debug console, `Ctrl+S` opens
+//! `show_flexi_logger_export_dialog` to save the log history to a file,
+//! `Ctrl+F` opens `show_module_filter_dialog` to filter it by target, and
+//! `Enter` opens `show_log_entry_details` for the selected entry. After
+//! `enable_log_spec_editing`, `Ctrl+L` opens `show_log_spec_dialog` to change
+//! what flexi_logger logs at all.
+
+use std::{mem, sync::Mutex};
 
-use cursive_core::{view::Nameable, views::Dialog, Cursive};
+use compact_str::{CompactString, ToCompactString};
+use cursive_core::{
+  event::Event,
+  theme::Effect,
+  utils::markup::StyledString,
+  view::{Nameable, Resizable, Scrollable},
+  views::{Dialog, EditView, LinearLayout, OnEventView, ScrollView, TextView},
+  Cursive,
+};
+use flexi_logger::{LogSpecification, LoggerHandle};
 use tap::Pipe;
 
-use crate::{FlexiLoggerView, FLEXI_LOGGER_DEBUG_VIEW_NAME};
+use crate::{
+  ExportFormat, ExportOptions, FlexiLoggerView, LogEntry, LogTheme, ModuleFilter,
+  ModuleFilterView, FLEXI_LOGGER_DEBUG_VIEW_NAME,
+};
+
+const EXPORT_PATH_VIEW_NAME: &str = "_flexi_debug_export_path";
+const MODULE_TREE_VIEW_NAME: &str = "_flexi_debug_module_tree";
+const MODULE_SPEC_VIEW_NAME: &str = "_flexi_debug_module_spec";
+const LOG_SPEC_VIEW_NAME: &str = "_flexi_debug_log_spec";
+
+/// The logger edited by `show_log_spec_dialog`.
+struct LogSpecEditing {
+  handle: LoggerHandle,
+  original: LogSpecification,
+  current: LogSpecification,
+}
+
+/// Never dropped (also not when replaced): dropping a clone of a
+/// `LoggerHandle` shuts the logger down.
+static LOG_SPEC_EDITING: Mutex<Option<LogSpecEditing>> = Mutex::new(None);
 
 /// Show the flexi_logger debug console.
 ///
@@ -50,10 +87,308 @@ use crate::{FlexiLoggerView, FLEXI_LOGGER_DEBUG_VIEW_NAME};
 /// ```
 pub fn show_flexi_logger_debug_console(siv: &mut Cursive) {
   FlexiLoggerView::new()
+    .with_theme(LogTheme::from_cursive_theme(siv.current_theme()))
     .wrap_scroll_view()
     .with_name(FLEXI_LOGGER_DEBUG_VIEW_NAME)
     .pipe(Dialog::around)
     .title("Debug console")
+    .pipe(OnEventView::new)
+    .on_event(Event::CtrlChar('s'), show_flexi_logger_export_dialog)
+    .on_event(Event::CtrlChar('f'), show_module_filter_dialog)
+    .on_event(Event::CtrlChar('l'), show_log_spec_dialog)
+    .pipe(|v| siv.add_layer(v))
+}
+
+/// Ask for a path, and export the history of the flexi_logger debug console
+/// to it (as plain text, with ANSI colors or as JSON lines).
+///
+/// The console must be visible. Bound to `Ctrl+S` inside the console.
+pub fn show_flexi_logger_export_dialog(siv: &mut Cursive) {
+  EditView::new()
+    .content("flexi_logger_debug_console.log")
+    .with_name(EXPORT_PATH_VIEW_NAME)
+    .fixed_width(48)
+    .pipe(Dialog::around)
+    .title("Export debug console")
+    .button("Text", |s| export_debug_console(s, ExportFormat::PlainText))
+    .button("ANSI", |s| export_debug_console(s, ExportFormat::Ansi))
+    .button("JSON", |s| export_debug_console(s, ExportFormat::JsonLines))
+    .dismiss_button("Cancel")
+    .pipe(|v| siv.add_layer(v))
+}
+
+fn export_debug_console(siv: &mut Cursive, format: ExportFormat) {
+  let Some(path) =
+    siv.call_on_name(EXPORT_PATH_VIEW_NAME, |v: &mut EditView| v.get_content())
+  else {
+    return;
+  };
+  siv.pop_layer();
+
+  let result = siv.call_on_name(
+    FLEXI_LOGGER_DEBUG_VIEW_NAME,
+    |v: &mut ScrollView<FlexiLoggerView>| {
+      v.get_inner()
+        .export(path.as_str(), ExportOptions::default().with_format(format))
+    },
+  );
+
+  match result {
+    Some(Ok(())) => format!("Exported to {path}"),
+    Some(Err(e)) => format!("Failed to export to {path}: {e}"),
+    _ => "The debug console is not visible".into(),
+  }
+  .pipe(Dialog::info)
+  .pipe(|v| siv.add_layer(v))
+}
+
+/// Edit the `ModuleFilter` of the flexi_logger debug console, as a tree of
+/// the targets of the retained entries (see `ModuleFilterView`) or as `RUST_LOG`-like
+/// directives (e.g. `info,my_app::net=trace,hyper=off`).
+///
+/// Changes are applied to the console right away. The console must be
+/// visible. Bound to `Ctrl+F` inside the console.
+pub fn show_module_filter_dialog(siv: &mut Cursive) {
+  let Some((targets, filter)) = siv.call_on_name(
+    FLEXI_LOGGER_DEBUG_VIEW_NAME,
+    |v: &mut ScrollView<FlexiLoggerView>| {
+      let view = v.get_inner();
+      (view.targets(), view.module_filter().clone())
+    },
+  ) else {
+    return;
+  };
+
+  let spec = EditView::new()
+    .content(filter.to_string())
+    .on_submit(|s, spec| match spec.parse::<ModuleFilter>() {
+      Ok(filter) => {
+        s.call_on_name(MODULE_TREE_VIEW_NAME, |v: &mut ModuleFilterView| {
+          v.set_filter(filter.clone())
+        });
+        apply_module_filter(s, &filter);
+      }
+      Err(e) => s.add_layer(Dialog::info(format!("Invalid directives: {e}"))),
+    })
+    .with_name(MODULE_SPEC_VIEW_NAME);
+
+  let tree = ModuleFilterView::new(targets, filter)
+    .with_on_change(|s, filter| {
+      s.call_on_name(MODULE_SPEC_VIEW_NAME, |v: &mut EditView| {
+        v.set_content(filter.to_string())
+      });
+      apply_module_filter(s, filter);
+    })
+    .with_name(MODULE_TREE_VIEW_NAME)
+    .scrollable()
+    .max_height(20);
+
+  LinearLayout::vertical()
+    .child(tree)
+    .child(TextView::new("\nDirectives (Enter applies)"))
+    .child(spec)
+    .pipe(Dialog::around)
+    .title("Filter by target")
+    .button("Reset", |s| {
+      s.call_on_name(MODULE_TREE_VIEW_NAME, |v: &mut ModuleFilterView| {
+        v.set_filter(ModuleFilter::default())
+      });
+      s.call_on_name(MODULE_SPEC_VIEW_NAME, |v: &mut EditView| v.set_content(""));
+      apply_module_filter(s, &ModuleFilter::default());
+    })
+    .dismiss_button("Close")
+    .min_width(40)
+    .pipe(|v| siv.add_layer(v))
+}
+
+fn apply_module_filter(siv: &mut Cursive, filter: &ModuleFilter) {
+  siv.call_on_name(
+    FLEXI_LOGGER_DEBUG_VIEW_NAME,
+    |v: &mut ScrollView<FlexiLoggerView>| {
+      v.get_inner_mut()
+        .set_module_filter(filter.clone());
+    },
+  );
+}
+
+/// Let the debug console change the log specification of the running
+/// logger (`Ctrl+L`, see `show_log_spec_dialog`).
+///
+/// `original` is the specification the logger was started with, which the
+/// dialog can reset to. A clone of `handle` is kept for the rest of the
+/// program.
+///
+/// Calling it again switches the dialog to the new `handle`. The clone of the
+/// previous handle is leaked rather than dropped, since dropping it would
+/// shut down that logger.
+///
+/// ```rust
+/// use cursive::Cursive;
+/// use cursive_logger_view::toggle::enable_log_spec_editing;
+/// use flexi_logger::{LogSpecification, Logger};
+///
+/// let siv = Cursive::default();
+///
+/// let spec = LogSpecification::env_or_parse("info").expect("valid spec");
+/// let handle = Logger::with(spec.clone())
+///     .log_to_writer(cursive_logger_view::boxed_flexi_log_writer(&siv))
+///     .start()
+///     .expect("failed to initialize logger!");
+///
+/// enable_log_spec_editing(&handle, spec);
+/// ```
+pub fn enable_log_spec_editing(handle: &LoggerHandle, original: LogSpecification) {
+  let previous = LOG_SPEC_EDITING
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .replace(LogSpecEditing {
+      handle: handle.clone(),
+      current: original.clone(),
+      original,
+    });
+  mem::forget(previous);
+}
+
+/// Ask for a new `RUST_LOG`-style log specification (e.g.
+/// `info, my_app::net = trace`) and apply it to the logger registered with
+/// `enable_log_spec_editing`.
+///
+/// Unlike the filters of the view, this changes which records are logged at
+/// all (also to the other writers of the logger). Bound to `Ctrl+L` inside
+/// the debug console.
+pub fn show_log_spec_dialog(siv: &mut Cursive) {
+  let Some(current) = LOG_SPEC_EDITING
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .as_ref()
+    .map(|editing| editing.current.to_string())
+  else {
+    "Log spec editing is not enabled (see `enable_log_spec_editing`)"
+      .pipe(Dialog::info)
+      .pipe(|v| siv.add_layer(v));
+    return;
+  };
+
+  EditView::new()
+    .content(current)
+    .on_submit(apply_log_spec)
+    .with_name(LOG_SPEC_VIEW_NAME)
+    .fixed_width(48)
+    .pipe(Dialog::around)
+    .title("Log specification")
+    .button("Apply", |s| {
+      if let Some(spec) =
+        s.call_on_name(LOG_SPEC_VIEW_NAME, |v: &mut EditView| v.get_content())
+      {
+        apply_log_spec(s, &spec);
+      }
+    })
+    .button("Reset", |s| {
+      let original = LOG_SPEC_EDITING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+        .map(|editing| {
+          editing
+            .handle
+            .set_new_spec(editing.original.clone());
+          editing.current = editing.original.clone();
+          editing.original.to_string()
+        });
+      if let Some(original) = original {
+        s.call_on_name(LOG_SPEC_VIEW_NAME, |v: &mut EditView| {
+          v.set_content(original)
+        });
+      }
+    })
+    .dismiss_button("Cancel")
+    .pipe(|v| siv.add_layer(v))
+}
+
+/// Validates and applies `spec`, closing the dialog if it is valid.
+fn apply_log_spec(siv: &mut Cursive, spec: &str) {
+  match LogSpecification::parse(spec) {
+    Ok(spec) => {
+      if let Some(editing) = LOG_SPEC_EDITING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_mut()
+      {
+        editing
+          .handle
+          .set_new_spec(spec.clone());
+        editing.current = spec;
+      }
+      siv.pop_layer();
+    }
+    Err(e) => siv.add_layer(Dialog::info(format!("Invalid log specification: {e}"))),
+  }
+}
+
+/// Show all fields of a log entry in a dialog.
+///
+/// `FlexiLoggerView` opens it when `Enter` is pressed on the selected entry.
+pub fn show_log_entry_details(siv: &mut Cursive, entry: &LogEntry) {
+  let mut text = StyledString::new();
+  let mut field = |name: &str, value: &str| {
+    text.append_styled(format!("{name:<10}"), Effect::Bold);
+    text.append_plain(value);
+    text.append_plain("\n");
+  };
+  let optional = |value: &Option<CompactString>| {
+    value
+      .as_deref()
+      .unwrap_or("-")
+      .to_compact_string()
+  };
+
+  field(
+    "Time",
+    &entry
+      .timestamp()
+      .format("%Y-%m-%d %H:%M:%S%.6f %:z")
+      .to_compact_string(),
+  );
+  field("Level", entry.level().as_str());
+  field("Target", entry.target());
+  field("Module", &optional(entry.module_path()));
+  field("File", &optional(entry.file()));
+  field(
+    "Line",
+    &entry
+      .line()
+      .map_or_else(|| "-".into(), |l| l.to_compact_string()),
+  );
+  field("Thread", &optional(entry.thread()));
+  if *entry.repeats() > 1 {
+    field(
+      "Repeats",
+      &entry
+        .repeats()
+        .to_compact_string(),
+    );
+    field(
+      "Last time",
+      &entry
+        .last_timestamp()
+        .format("%Y-%m-%d %H:%M:%S%.6f %:z")
+        .to_compact_string(),
+    );
+  }
+  entry
+    .key_values()
+    .iter()
+    .for_each(|(key, value)| field(key, value));
+
+  text.append_styled("\nMessage\n", Effect::Bold);
+  text.append_plain(entry.message().as_str());
+
+  TextView::new(text)
+    .scrollable()
+    .pipe(Dialog::around)
+    .title("Log entry")
+    .dismiss_button("Close")
+    .max_width(100)
     .pipe(|v| siv.add_layer(v))
 }
 
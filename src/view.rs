@@ -1,16 +1,62 @@
+use std::{borrow::Cow, ops::RangeInclusive};
+
+use compact_str::{format_compact, CompactString};
 use cursive_core::{
-  view::{ScrollStrategy, Scrollable, View},
+  direction::Direction,
+  event::{Event, EventResult, Key},
+  theme::Effect,
+  utils::markup::StyledString,
+  view::{CannotFocus, ScrollStrategy, Scrollable, View},
   views::ScrollView,
-  Printer, Vec2,
+  Printer, Rect, Vec2,
 };
+use log::LevelFilter;
 use tap::Pipe;
-use unicode_width::UnicodeWidthStr;
+
+use tinyvec::TinyVec;
 
 use crate::{
-  log_buffer::{self, GET_LOCK_ERR_MSG},
-  FlexiLoggerView,
+  clipboard::{self, Copied},
+  export,
+  log_buffer::LogBuffer,
+  parse_format,
+  search::SearchEvent,
+  toggle::show_log_entry_details,
+  wrap, CopyMethod, FlexiLoggerView, FormatItem, FormatParseError, KeyFilter,
+  LogChannel, LogEntry, LogTheme, ModuleFilter,
 };
 
+/// A single rendered line of the view.
+#[derive(Debug, Default)]
+pub(crate) struct Line {
+  pub(crate) x: usize,
+  pub(crate) text: StyledString,
+}
+
+impl Line {
+  pub(crate) fn width(&self) -> usize {
+    self.x + self.text.width()
+  }
+}
+
+/// Everything that affects how entries are rendered into lines.
+///
+/// When it changes, the `LineIndex` has to be rebuilt.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RenderKey {
+  indent: bool,
+  wrap_width: Option<usize>,
+  /// Hidden columns, if in table mode.
+  table: Option<Vec<usize>>,
+  format: TinyVec<[FormatItem; 8]>,
+  time_format: CompactString,
+  theme: LogTheme,
+  level_filter: LevelFilter,
+  key_filter: Option<KeyFilter>,
+  module_filter: ModuleFilter,
+  search: Option<(CompactString, bool)>,
+}
+
 impl FlexiLoggerView {
   /// Wraps a `FlexiLoggerView` in a `ScrollView`.
   ///
@@ -24,95 +70,608 @@ impl FlexiLoggerView {
   ///     .wrap_scroll_view();
   /// ```
   pub fn wrap_scroll_view(self) -> ScrollView<Self> {
+    // Wrapped lines never need horizontal scrolling
+    let scroll_x = !self.wrap;
+
     self
       .scrollable()
-      .scroll_x(true)
+      .scroll_x(scroll_x)
       .scroll_y(true)
       .scroll_strategy(ScrollStrategy::StickToBottom)
+      .on_scroll_inner(|scroll_view, _| {
+        // e.g. jumping to a search hit must not be undone by `StickToBottom`
+        match scroll_view
+          .get_inner_mut()
+          .scroll_request
+          .take()
+        {
+          Some(strategy) => scroll_view.set_scroll_strategy(strategy),
+          _ => EventResult::Ignored,
+        }
+      })
   }
 
   /// Creates a new `FlexiLoggerView`.
   pub fn new() -> Self {
-    FlexiLoggerView { indent: true }
+    use crate::LogItems::{DateTime, Level, Message, ModLine};
+
+    FlexiLoggerView {
+      indent: true,
+      wrap: false,
+      wrap_width: None,
+      table: false,
+      columns: Default::default(),
+      format: [DateTime, Level, ModLine, Message]
+        .into_iter()
+        .map(FormatItem::from)
+        .collect(),
+      time_format: "%T%.3f".pipe(CompactString::const_new),
+      theme: LogTheme::default(),
+      level_filter: LevelFilter::max(),
+      key_filter: None,
+      module_filter: ModuleFilter::default(),
+      channel: LogChannel::global(),
+      search: Default::default(),
+      index: Default::default(),
+      scroll_request: None,
+      snapshot: None,
+      new_records: 0,
+      selected: None,
+      anchor: None,
+      copy_method: CopyMethod::default(),
+      notice: None,
+    }
+  }
+
+  /// Sets the `format` from a template like
+  /// `"{time} [{level:>5}] {target}: {message}"`, see `parse_format`.
+  pub fn with_format_str(self, template: &str) -> Result<Self, FormatParseError> {
+    parse_format(template).map(|format| self.with_format(format))
+  }
+
+  /// Whether an entry passes the filters of this view.
+  pub(crate) fn is_visible(&self, entry: &LogEntry) -> bool {
+    *entry.level() <= self.level_filter
+      && self
+        .key_filter
+        .as_ref()
+        .is_none_or(|filter| filter.matches(entry))
+      && self
+        .module_filter
+        .matches(entry)
+  }
+
+  /// Targets of the entries retained in the channel (or the paused
+  /// snapshot), sorted.
+  pub fn targets(&self) -> Vec<CompactString> {
+    self.with_logs(|logs| {
+      logs
+        .targets()
+        .into_iter()
+        .cloned()
+        .collect()
+    })
+  }
+
+  /// Splits a rendered entry into lines (and soft-wraps them in `wrap` mode).
+  ///
+  /// Continuation lines of a multi-line (or wrapped) message are indented to
+  /// `message_x`, the start of the message (if `indent` is enabled). Without
+  /// it (e.g. in table mode), lines after a line break are indented to the
+  /// span containing it.
+  fn split_lines(&self, msg: &StyledString, message_x: Option<usize>) -> Vec<Line> {
+    let mut lines = vec![Line::default()];
+    let mut prefix_width = 0;
+    let mut line_broken = false;
+
+    for span in msg.spans() {
+      for (i, part) in span
+        .content
+        .split('\n')
+        .enumerate()
+      {
+        if i > 0 {
+          line_broken = true;
+          lines.push(Line {
+            x: match self.indent {
+              true => message_x.unwrap_or(prefix_width),
+              _ => 0,
+            },
+            text: StyledString::new(),
+          });
+        }
+        if let Some(line) = lines.last_mut() {
+          line
+            .text
+            .append_styled(part, *span.attr)
+        }
+      }
+      if !line_broken {
+        prefix_width += span.width;
+      }
+    }
+
+    match (self.wrap && !self.table, self.wrap_width) {
+      (true, Some(width)) => {
+        let continuation_x = match (self.indent, message_x, line_broken) {
+          (false, ..) => 0,
+          (_, Some(x), _) => x,
+          (_, _, true) => prefix_width,
+          _ => 0,
+        };
+
+        lines
+          .into_iter()
+          .flat_map(|line| wrap::wrap_line(line, width, continuation_x))
+          .collect()
+      }
+      _ => lines,
+    }
+  }
+
+  /// Renders an entry into its lines (as a table row with the column
+  /// `widths`, in table mode).
+  pub(crate) fn entry_lines(
+    &self,
+    entry: &LogEntry,
+    widths: Option<&[usize]>,
+  ) -> Vec<Line> {
+    let (msg, message_x) = match self.table {
+      true => (self.render_row(entry, widths), None),
+      _ => self.render_entry(entry),
+    };
+    self.split_lines(&msg, message_x)
+  }
+
+  fn render_key(&self) -> RenderKey {
+    RenderKey {
+      indent: self.indent,
+      wrap_width: self
+        .wrap
+        .then_some(self.wrap_width)
+        .flatten(),
+      table: self
+        .table
+        .then(|| self.columns.hidden().to_vec()),
+      format: self.format.clone(),
+      time_format: self.time_format.clone(),
+      theme: self.theme,
+      level_filter: self.level_filter,
+      key_filter: self.key_filter.clone(),
+      module_filter: self.module_filter.clone(),
+      search: self.search.key(),
+    }
+  }
+
+  fn update_index(&mut self) {
+    let mut index = core::mem::take(&mut self.index);
+    index.update(self.render_key(), self);
+    self.index = index;
+  }
+
+  /// Calls `f` with the displayed log buffer: the snapshot taken when the
+  /// view was paused, or the (locked) buffer of the channel.
+  pub(crate) fn with_logs<R>(&self, f: impl FnOnce(&LogBuffer) -> R) -> R {
+    match &self.snapshot {
+      Some(snapshot) => f(snapshot),
+      _ => f(&self.channel.lock()),
+    }
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.snapshot.is_some()
+  }
+
+  /// Pauses or resumes the live tail.
+  ///
+  /// While paused, the view shows a frozen snapshot of its channel (which
+  /// keeps receiving records) and a "N new records" indicator. Resuming sticks
+  /// the wrapping `ScrollView` to the bottom again.
+  pub fn set_paused(&mut self, paused: bool) {
+    match (paused, self.is_paused()) {
+      (true, false) => {
+        self.snapshot = self
+          .channel
+          .lock()
+          .clone()
+          .pipe(Some)
+      }
+      (false, true) => {
+        self.snapshot = None;
+        self.new_records = 0;
+        self.scroll_request = Some(ScrollStrategy::StickToBottom);
+      }
+      _ => {}
+    }
+  }
+
+  /// A copy of the selected entry, if any.
+  pub fn selected_entry(&self) -> Option<LogEntry> {
+    self
+      .selected
+      .and_then(|seq| self.with_logs(|logs| logs.get(seq).cloned()))
+  }
+
+  /// Sequence numbers of the selected entries.
+  fn selection(&self) -> Option<RangeInclusive<u64>> {
+    let selected = self.selected?;
+    let anchor = self.anchor.unwrap_or(selected);
+
+    Some(selected.min(anchor)..=selected.max(anchor))
+  }
+
+  /// Copies the selected (visible) entries as plain text.
+  fn copy_selection(&mut self) {
+    let Some(selection) = self.selection() else {
+      return;
+    };
+
+    let (text, count) = self.with_logs(|logs| {
+      logs
+        .iter_from(*selection.start())
+        .take_while(|(_, seq)| selection.contains(seq))
+        .filter(|(entry, _)| self.is_visible(entry))
+        .fold((String::new(), 0), |(mut text, count), (entry, _)| {
+          self
+            .entry_lines(entry, None)
+            .iter()
+            .for_each(|line| {
+              text.push_str(&" ".repeat(line.x));
+              text.push_str(&export::plain_line(&line.text));
+              text.push('\n');
+            });
+          (text, count + 1)
+        })
+    });
+
+    self.notice = match clipboard::copy(&text, self.copy_method) {
+      Ok(Copied::Clipboard) => format_compact!("Copied {count} entries"),
+      Ok(Copied::File(path)) => {
+        format_compact!("Copied {count} entries to {}", path.display())
+      }
+      Err(e) => format_compact!("Failed to copy: {e}"),
+    }
+    .into();
+    self.anchor = None;
+  }
+
+  /// Moves the selection `offset` entries up or down.
+  ///
+  /// Ignores the event if the selection can not move (e.g. at the first
+  /// entry), so the arrow keys can move the focus to other views.
+  fn select(&mut self, offset: isize) -> EventResult {
+    let selected = self
+      .index
+      .step(self.selected, offset);
+    if selected == self.selected {
+      return EventResult::Ignored;
+    }
+
+    self.selected = selected;
+    self.scroll_request = Some(ScrollStrategy::KeepRow);
+    EventResult::Consumed(None)
+  }
+
+  /// Text for the status bar (if there is anything to show).
+  fn status(&self) -> Option<CompactString> {
+    let pause = self.is_paused().then(|| {
+      format_compact!("PAUSED, {} new records (p: follow)", self.new_records)
+    });
+    let search = self
+      .search
+      .is_active()
+      .then(|| self.search.status());
+
+    let dropped = match self.channel.dropped() {
+      0 => None,
+      n => Some(format_compact!("{n} records dropped")),
+    };
+
+    [pause, dropped, search, self.notice.clone()]
+      .into_iter()
+      .flatten()
+      .reduce(|status, part| format_compact!("{status} | {part}"))
   }
 }
 
 impl View for FlexiLoggerView {
   fn draw(&self, printer: &Printer<'_, '_>) {
-    let logs = log_buffer::static_logs()
-      .lock()
-      .expect(GET_LOCK_ERR_MSG);
-
-    // Only print the last logs, so skip what doesn't fit
-    let skipped = logs
-      .len()
-      .saturating_sub(printer.size.y);
-
-    logs
-      .iter()
-      .skip(skipped)
-      .fold(0, |y, msg| {
-        let log_msg_index = msg.spans_raw().len() - 1;
-
-        let x = msg
-          .spans()
-          .take(log_msg_index)
-          .fold(0, |x, span| {
-            printer.with_style(*span.attr, |p| p.print((x, y), span.content));
-            x + span.width
-          });
+    // The table header sticks to the top row
+    let header = usize::from(self.table);
+
+    // Only print the last lines, so skip what doesn't fit
+    let skipped = (self.index.total_lines() + header).saturating_sub(printer.size.y);
+
+    let top = (skipped + printer.content_offset.y).saturating_sub(header);
+    let bottom = (skipped + printer.content_offset.y + printer.output_size.y)
+      .saturating_sub(header);
+
+    self.with_logs(|logs| {
+      let entries = self
+        .index
+        .window(top, bottom)
+        .filter_map(|(seq, first_line)| Some((seq, logs.get(seq)?, first_line)))
+        .collect::<Vec<_>>();
 
-        msg
-          .spans()
-          .nth(log_msg_index)
-          .map(|log_msg| {
-            log_msg
-              .content
-              .split('\n')
-              .enumerate()
-              .fold(y, |current_y, (i, part)| {
-                let x_pos = if !self.indent && i > 0 { 0 } else { x };
-                printer
-                  .with_style(*log_msg.attr, |p| p.print((x_pos, current_y), part));
-                current_y + 1
-              })
-          })
-          .unwrap_or(y)
+      // Columns are sized to fit the visible rows
+      let widths = self.table.then(|| {
+        entries
+          .iter()
+          .map(|(_, entry, _)| *entry)
+          .pipe(|entries| self.column_widths(entries))
       });
-  }
 
-  fn required_size(&mut self, constraint: Vec2) -> Vec2 {
-    let logs = log_buffer::static_logs()
-      .lock()
-      .expect(GET_LOCK_ERR_MSG);
+      entries
+        .iter()
+        .for_each(|(seq, entry, first_line)| {
+          let selected = self
+            .selection()
+            .is_some_and(|selection| selection.contains(seq));
 
-    // The longest line sets the width
-    let width = logs
+          self
+            .entry_lines(entry, widths.as_deref())
             .iter()
-            .map(|msg| {
-                msg.spans()
-                    .map(|x|
-                    // if the log message contains more than one line,
-                    // only the longest line should be considered
-                    // (definitely not the total content.len())
-                    x.content.split('\n').map(|x| x.width()).max().expect("ERR: required_size(), failed to get width"))
-                    .sum::<usize>()
+            .zip(*first_line..)
+            .filter(|(_, y)| (top..bottom).contains(y))
+            .for_each(|(line, y)| {
+              let text = match self.search.is_active() {
+                true => self
+                  .search
+                  .highlight(&line.text)
+                  .pipe(Cow::Owned),
+                _ => Cow::Borrowed(&line.text),
+              };
+              let row = y + header - skipped;
+              match selected {
+                true => printer.with_effect(Effect::Reverse, |p| {
+                  p.print_hline((0, row), printer.size.x, " ");
+                  p.print_styled((line.x, row), text.as_ref());
+                }),
+                _ => printer.print_styled((line.x, row), text.as_ref()),
+              }
             })
-            .max()
-            .unwrap_or(1)
-            .pipe(|w|core::cmp::max(w, constraint.x));
+        });
 
-    let height = logs
-            .iter()
-            .map(|msg| {
-                msg.spans()
-                    .last()
-                    .map(|x| x.content.split('\n').count())
-                    .expect("ERR: required_size(), the last span message is invalid, and failed to get height.")
-            })
-            .sum::<usize>()
-            .pipe(|h| core::cmp::max(h, constraint.y));
+      if let (Some(widths), true) = (widths, printer.output_size.y > 0) {
+        printer.print_hline(printer.content_offset, printer.output_size.x, " ");
+        printer
+          .print_styled((0, printer.content_offset.y), &self.render_header(&widths));
+      }
+    });
+
+    if let (Some(status), true) = (self.status(), printer.output_size.y > 0) {
+      let status_bar = printer.content_offset + (0, printer.output_size.y - 1);
+
+      printer.with_effect(Effect::Reverse, |p| {
+        p.print_hline(status_bar, printer.output_size.x, " ");
+        p.print(status_bar, &status);
+      });
+    }
+  }
+
+  fn layout(&mut self, size: Vec2) {
+    // Only the final size decides the wrap width, otherwise the different
+    // constraints probed by `required_size` would rebuild the index each time.
+    if self.wrap && size.x > 0 {
+      self.wrap_width = Some(size.x);
+    }
+    self.update_index();
+    self
+      .index
+      .hits()
+      .pipe(|hits| self.search.set_hits(hits));
+
+    if let Some(snapshot) = &self.snapshot {
+      let logs = self.channel.lock();
+      let visible_repeats = |entry: Option<&LogEntry>| {
+        entry
+          .filter(|entry| self.is_visible(entry))
+          .map_or(0, |entry| *entry.repeats())
+      };
+      // Records merged into the last entry of the snapshot (by dedup) count
+      // as new, too
+      let last = snapshot
+        .next_seq()
+        .checked_sub(1)
+        .filter(|&seq| seq >= snapshot.front_seq());
+      let merged = last.map_or(0, |seq| {
+        visible_repeats(logs.get(seq))
+          .saturating_sub(visible_repeats(snapshot.get(seq)))
+      });
+
+      self.new_records = logs
+        .iter_from(snapshot.next_seq())
+        .map(|(entry, _)| visible_repeats(Some(entry)))
+        .sum::<u64>()
+        + merged;
+    }
+  }
+
+  fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+    if self.wrap && constraint.x > 0 {
+      self
+        .wrap_width
+        .get_or_insert(constraint.x);
+    }
+    self.update_index();
+
+    // The longest line sets the width (unless we wrap at the constraint)
+    let longest = match self.table {
+      true => self.index.table_width(self),
+      _ => self.index.width(),
+    };
+    let width = match self.wrap && !self.table {
+      true => constraint.x,
+      _ => longest
+        .max(1)
+        .pipe(|w| core::cmp::max(w, constraint.x)),
+    };
+
+    let height = (self.index.total_lines() + usize::from(self.table))
+      .pipe(|h| core::cmp::max(h, constraint.y));
 
     Vec2::new(width, height)
   }
+
+  fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+    // We need the focus to receive the level filter and search keys.
+    Ok(EventResult::Consumed(None))
+  }
+
+  fn on_event(&mut self, event: Event) -> EventResult {
+    use LevelFilter::{Debug, Error, Info, Trace, Warn};
+
+    self.notice = None;
+
+    match self.search.on_event(&event) {
+      SearchEvent::Ignored => {}
+      SearchEvent::Consumed => return EventResult::Consumed(None),
+      SearchEvent::Jump => {
+        self.selected = None;
+        self.scroll_request = Some(ScrollStrategy::KeepRow);
+        return EventResult::Consumed(None);
+      }
+    }
+
+    match event {
+      Event::Key(Key::Up) => return self.select(-1),
+      Event::Key(Key::Down) => return self.select(1),
+      Event::Key(Key::Esc) if self.selected.is_some() => {
+        (self.selected, self.anchor) = (None, None);
+        return EventResult::Consumed(None);
+      }
+      Event::Char('v') => {
+        self.anchor = match self.anchor {
+          Some(_) => None,
+          _ => {
+            self.selected = self
+              .selected
+              .or_else(|| self.index.step(None, 0));
+            self.selected
+          }
+        };
+        return EventResult::Consumed(None);
+      }
+      Event::Char('y') if self.selected.is_some() => {
+        self.copy_selection();
+        return EventResult::Consumed(None);
+      }
+      Event::Key(Key::Enter) => {
+        if let Some(entry) = self.selected_entry() {
+          return EventResult::with_cb(move |siv| {
+            show_log_entry_details(siv, &entry)
+          });
+        }
+      }
+      _ => {}
+    }
+
+    if self.table
+      && self
+        .columns
+        .on_event(&event, self.column_count())
+    {
+      return EventResult::Consumed(None);
+    }
+
+    let level_filter = match event {
+      Event::Char('t') => {
+        self.table = !self.table;
+        return EventResult::Consumed(None);
+      }
+      Event::Char('p') => {
+        self.set_paused(!self.is_paused());
+        return EventResult::Consumed(None);
+      }
+      Event::Char('1') => Error,
+      Event::Char('2') => Warn,
+      Event::Char('3') => Info,
+      Event::Char('4') => Debug,
+      Event::Char('5') => Trace,
+      _ => return EventResult::Ignored,
+    };
+    self.level_filter = level_filter;
+
+    EventResult::Consumed(None)
+  }
+
+  fn important_area(&self, view_size: Vec2) -> Rect {
+    let header = usize::from(self.table);
+    let selected = self
+      .selected
+      .and_then(|seq| self.index.lines_of(seq));
+
+    match (&self.scroll_request, selected, self.search.current_line()) {
+      (Some(_), Some(lines), _) => {
+        Rect::from_size((0, lines.start + header), (view_size.x, lines.len()))
+      }
+      (Some(_), _, Some(y)) => Rect::from_size((0, y + header), (view_size.x, 1)),
+      _ => Rect::from_size((0, 0), view_size),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// `(x, text)` of the lines of an `Info` entry, wrapped at `width`.
+  fn lines(format: &str, message: &str, width: usize) -> Vec<(usize, String)> {
+    let mut view = FlexiLoggerView::new()
+      .with_format_str(format)
+      .unwrap()
+      .with_wrap(true);
+    view.indent = true;
+    view.wrap_width = Some(width);
+
+    view
+      .entry_lines(&LogEntry::test("t", message), None)
+      .into_iter()
+      .map(|line| (line.x, line.text.source().to_owned()))
+      .collect()
+  }
+
+  #[test]
+  fn wrapped_message_before_a_suffix() {
+    // Wrapped at the start of the message, not at the start of the suffix
+    assert_eq!(
+      lines("{level:6}|{message}|{target}", "aaa bbb ccc", 12),
+      [
+        (0, "INFO  |aaa".into()),
+        (7, "bbb".into()),
+        (7, "ccc|t".into()),
+      ]
+    );
+  }
+
+  #[test]
+  fn padded_message() {
+    assert_eq!(
+      lines("{level} {message:>6}.", "a\nb", 80),
+      [(0, "INFO      a".into()), (10, "b.".into())]
+    );
+  }
+
+  #[test]
+  fn arrows_are_ignored_at_the_ends() {
+    let channel = LogChannel::new("select");
+    let mut view = FlexiLoggerView::new().with_channel(channel.clone());
+    let consumed = |view: &mut FlexiLoggerView, key| {
+      matches!(view.on_event(Event::Key(key)), EventResult::Consumed(_))
+    };
+
+    assert!(!consumed(&mut view, Key::Up));
+
+    channel.push(LogEntry::test("t", "a"));
+    channel.push(LogEntry::test("t", "b"));
+    view.update_index();
+
+    // The first `Up` selects the last entry
+    assert!(consumed(&mut view, Key::Up));
+    assert!(!consumed(&mut view, Key::Down));
+    assert!(consumed(&mut view, Key::Up));
+    assert!(!consumed(&mut view, Key::Up));
+    assert_eq!(view.selected, Some(0));
+    assert!(consumed(&mut view, Key::Down));
+  }
 }
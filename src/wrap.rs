@@ -0,0 +1,97 @@
+use cursive_core::{theme::Style, utils::markup::StyledString};
+use unicode_width::UnicodeWidthChar;
+
+use crate::view::Line;
+
+/// Soft-wraps a line at `width` columns.
+///
+/// Lines are broken after the last whitespace that fits, or in the middle of
+/// a word if there is none. Continuation lines start at `continuation_x`
+/// (falling back to `0` if that leaves no room).
+pub(crate) fn wrap_line(
+  line: Line,
+  width: usize,
+  continuation_x: usize,
+) -> Vec<Line> {
+  let chars = line
+    .text
+    .spans()
+    .flat_map(|span| {
+      span
+        .content
+        .chars()
+        .map(move |c| (c, *span.attr, c.width().unwrap_or(0)))
+    })
+    .collect::<Vec<_>>();
+
+  if line.x
+    + chars
+      .iter()
+      .map(|(.., w)| w)
+      .sum::<usize>()
+    <= width
+  {
+    return vec![line];
+  }
+
+  let continuation_x = match continuation_x < width {
+    true => continuation_x,
+    _ => 0,
+  };
+
+  let mut lines = Vec::new();
+  let (mut start, mut x, mut line_x) = (0, line.x, line.x);
+  let mut last_space = None;
+  let mut i = 0;
+
+  while i < chars.len() {
+    let (c, _, w) = chars[i];
+
+    // Always keep at least one char per line, so we make progress.
+    if x + w > width && i > start {
+      let end = last_space
+        .filter(|&s| s > start)
+        .unwrap_or(i);
+
+      lines.push(Line {
+        x: line_x,
+        text: styled(&chars[start..end]),
+      });
+
+      // Skip the whitespace we broke at
+      start = match chars.get(end) {
+        Some((c, ..)) if c.is_whitespace() => end + 1,
+        _ => end,
+      };
+      (x, line_x, last_space, i) = (continuation_x, continuation_x, None, start);
+      continue;
+    }
+
+    if c.is_whitespace() {
+      last_space = Some(i);
+    }
+    x += w;
+    i += 1;
+  }
+
+  lines.push(Line {
+    x: line_x,
+    text: styled(&chars[start..]),
+  });
+
+  lines
+}
+
+/// Re-assembles chars into spans of the same style.
+pub(crate) fn styled(chars: &[(char, Style, usize)]) -> StyledString {
+  chars
+    .chunk_by(|(_, a, _), (_, b, _)| a == b)
+    .fold(StyledString::new(), |mut text, chunk| {
+      let content = chunk
+        .iter()
+        .map(|(c, ..)| c)
+        .collect::<String>();
+      text.append_styled(content, chunk[0].1);
+      text
+    })
+}